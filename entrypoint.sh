@@ -1,5 +1,6 @@
 #!/bin/sh
 
-chown appuser:appgroup /data/db.json
+# Saves write temporary files, backups and journals next to the database
+chown -R appuser:appgroup /data
 
 exec su-exec appuser "$@"
@@ -0,0 +1,157 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests go through
+    Closed,
+    /// Too many failures in a row, requests are rejected until the cooldown is over
+    Open,
+    /// Cooldown is over, a single probe request decides whether to close or open again
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub retry_in_second: Option<u64>,
+}
+
+impl CircuitStatus {
+    pub fn is_open(&self) -> bool {
+        self.state == CircuitState::Open
+    }
+}
+
+#[derive(Debug)]
+struct CircuitInner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// When the half open probe was let through, until it reports back
+    probe_sent_at: Option<Instant>,
+}
+
+/// Stops hammering u.gg once it looks down, and tries again after a cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    inner: Mutex<CircuitInner>,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            inner: Mutex::new(CircuitInner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                probe_sent_at: None,
+            }),
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+        }
+    }
+
+    /// Whether a request may be sent, moving to half open once the cooldown is over. Only one
+    /// probe goes through while half open, another one if it did not report back in a cooldown.
+    pub fn allow_request(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+
+        match inner.state {
+            CircuitState::Closed => true,
+            CircuitState::HalfOpen => {
+                let probe_lost = inner
+                    .probe_sent_at
+                    .is_none_or(|probe_sent_at| probe_sent_at.elapsed() >= self.cooldown);
+
+                if probe_lost {
+                    inner.probe_sent_at = Some(Instant::now());
+                }
+                probe_lost
+            }
+            CircuitState::Open => {
+                let cooldown_over = inner
+                    .opened_at
+                    .is_none_or(|opened_at| opened_at.elapsed() >= self.cooldown);
+
+                if cooldown_over {
+                    info!("Circuit breaker half open, trying u.gg again");
+                    inner.state = CircuitState::HalfOpen;
+                    inner.probe_sent_at = Some(Instant::now());
+                }
+                cooldown_over
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.state != CircuitState::Closed {
+            info!("Circuit breaker closed, u.gg is answering again");
+        }
+        inner.state = CircuitState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        inner.probe_sent_at = None;
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        inner.probe_sent_at = None;
+
+        let should_open = match inner.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => inner.consecutive_failures >= self.failure_threshold,
+            CircuitState::Open => false,
+        };
+
+        if should_open {
+            error!(
+                "Circuit breaker open after {} consecutive failures, pausing for {}s",
+                inner.consecutive_failures,
+                self.cooldown.as_secs()
+            );
+            inner.state = CircuitState::Open;
+            inner.opened_at = Some(Instant::now());
+        } else if inner.state == CircuitState::Closed {
+            warn!(
+                "u.gg failure {}/{} before opening the circuit breaker",
+                inner.consecutive_failures, self.failure_threshold
+            );
+        }
+    }
+
+    pub fn status(&self) -> CircuitStatus {
+        let inner = self.inner.lock().unwrap();
+
+        let remaining = inner
+            .opened_at
+            .map(|opened_at| self.cooldown.saturating_sub(opened_at.elapsed()));
+
+        // An open circuit whose cooldown is over lets the next request through
+        let (state, retry_in_second) = match (inner.state, remaining) {
+            (CircuitState::Open, Some(remaining)) if !remaining.is_zero() => {
+                (CircuitState::Open, Some(remaining.as_secs()))
+            }
+            (CircuitState::Open, _) => (CircuitState::HalfOpen, None),
+            (state, _) => (state, None),
+        };
+
+        CircuitStatus {
+            state,
+            consecutive_failures: inner.consecutive_failures,
+            retry_in_second,
+        }
+    }
+}
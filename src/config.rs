@@ -1,20 +1,88 @@
 use std::env;
 
+use chrono::TimeDelta;
+
 const DEFAULT_CRON_INTERVAL_MINUTE: u32 = 60;
+const DEFAULT_HISTORY_RETENTION_DAY: u32 = 365;
+const DEFAULT_LEADERBOARD_MIN_MATCHES: i32 = 10;
+const DEFAULT_DATABASE_IMPORT_PATH: &str = ".db.json";
+const DEFAULT_DATABASE_BACKUP_COUNT: usize = 5;
 const DEFAULT_HTTP_PORT: u16 = 8000;
+const DEFAULT_UGG_API_URL: &str = "https://u.gg/api/rematch/profiles";
+const DEFAULT_FIXTURE_DIR: &str = "fixtures";
+const DEFAULT_TEMPLATES_PATH: &str = "templates.json";
+const DEFAULT_SCRAPER_CONCURRENCY: usize = 4;
+const DEFAULT_SCRAPER_PLAYER_TIMEOUT_SECOND: u64 = 30;
+const DEFAULT_SCRAPER_RETRIES: u32 = 2;
+const DEFAULT_SCRAPER_RETRY_BASE_DELAY_MS: u64 = 1000;
+const DEFAULT_CIRCUIT_BREAKER_THRESHOLD: u32 = 5;
+const DEFAULT_CIRCUIT_BREAKER_COOLDOWN_MINUTE: u32 = 30;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum DatabaseBackend {
+    /// A single JSON file
+    #[default]
+    Json,
+    /// An embedded SQLite database
+    Sqlite,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum StatsSourceKind {
+    /// Headless Chromium visiting the u.gg profile page
+    #[default]
+    Browser,
+    /// Direct calls to the u.gg profiles API
+    Http,
+    /// JSON files read from a local directory
+    Fixture,
+}
 
 #[derive(Debug, Default, Clone)]
 pub struct Config {
     pub discord_token: String,
     pub discord_server_id: Option<String>,
+    /// Guild the players registered before multi-guild support are moved to
+    pub default_guild_id: Option<u64>,
 
     pub http_port: u16,
     pub admin_api_key: String,
 
     pub database_path: String,
+    pub database_backend: DatabaseBackend,
+    /// JSON database imported on the first start of the SQLite backend
+    pub database_import_path: Option<String>,
+    /// Previous versions of the JSON database kept next to it
+    pub database_backup_count: usize,
 
     pub cron_interval_minute: u32,
     pub skip_cron: bool,
+
+    /// Profiles are fetched once before being registered, to catch typos
+    pub register_trial_fetch: bool,
+
+    /// Stats snapshots older than that are dropped, 0 keeps them forever
+    pub history_retention_day: u32,
+
+    /// Players with fewer games come last in the leaderboard sorted by win rate
+    pub leaderboard_min_matches: i32,
+
+    /// Variants of the bot replies, read again whenever the file changes
+    pub templates_path: String,
+
+    pub stats_source: StatsSourceKind,
+    pub ugg_api_url: String,
+    pub fixture_dir: String,
+    /// Raw u.gg payloads are saved there when set, to investigate API changes
+    pub ugg_debug_dir: Option<String>,
+
+    pub scraper_concurrency: usize,
+    pub scraper_player_timeout_second: u64,
+    pub scraper_retries: u32,
+    pub scraper_retry_base_delay_ms: u64,
+
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_cooldown_minute: u32,
 }
 
 impl Config {
@@ -22,6 +90,13 @@ impl Config {
         let discord_token = env::var("DISCORD_TOKEN").expect("Configure your discord token bro!");
         let discord_server_id = env::var("DISCORD_SERVER_ID").ok();
 
+        // `.env.example` ships it blank, which must fall back as well
+        let default_guild_id = env::var("DEFAULT_GUILD_ID")
+            .ok()
+            .filter(|guild_id| !guild_id.trim().is_empty())
+            .or(discord_server_id.clone())
+            .and_then(|guild_id| guild_id.trim().parse().ok());
+
         let http_port = env::var("HTTP_PORT")
             .ok()
             .and_then(|port| port.trim().parse().ok())
@@ -30,6 +105,27 @@ impl Config {
         let admin_api_key = env::var("ADMIN_API_KEY").ok().unwrap_or(String::from(""));
 
         let database_path = env::var("DATABASE_PATH").expect("Configure your database path bro!");
+        let (database_path, database_backend) = parse_database_path(&database_path);
+        let database_backend = match env::var("DATABASE_BACKEND")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "json" => DatabaseBackend::Json,
+            "sqlite" => DatabaseBackend::Sqlite,
+            _ => database_backend,
+        };
+
+        let database_import_path = env::var("DATABASE_IMPORT_PATH")
+            .ok()
+            .or(Some(String::from(DEFAULT_DATABASE_IMPORT_PATH)))
+            .filter(|path| !path.trim().is_empty());
+
+        let database_backup_count = env::var("DATABASE_BACKUP_COUNT")
+            .ok()
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap_or(DEFAULT_DATABASE_BACKUP_COUNT);
 
         let cron_interval_minute = env::var("CRON_INTERVAL_MINUTE")
             .ok()
@@ -41,14 +137,127 @@ impl Config {
             .parse()
             .unwrap_or(false);
 
+        let register_trial_fetch: bool = env::var("REGISTER_TRIAL_FETCH")
+            .unwrap_or(String::from("true"))
+            .parse()
+            .unwrap_or(true);
+
+        let history_retention_day = env::var("HISTORY_RETENTION_DAY")
+            .ok()
+            .and_then(|retention| retention.trim().parse().ok())
+            .unwrap_or(DEFAULT_HISTORY_RETENTION_DAY);
+
+        let leaderboard_min_matches = env::var("LEADERBOARD_MIN_MATCHES")
+            .ok()
+            .and_then(|matches| matches.trim().parse().ok())
+            .unwrap_or(DEFAULT_LEADERBOARD_MIN_MATCHES);
+
+        let stats_source = match env::var("STATS_SOURCE")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "http" => StatsSourceKind::Http,
+            "fixture" => StatsSourceKind::Fixture,
+            _ => StatsSourceKind::Browser,
+        };
+
+        let ugg_api_url = env::var("UGG_API_URL").unwrap_or(String::from(DEFAULT_UGG_API_URL));
+
+        let fixture_dir = env::var("FIXTURE_DIR").unwrap_or(String::from(DEFAULT_FIXTURE_DIR));
+
+        let templates_path =
+            env::var("TEMPLATES_PATH").unwrap_or(String::from(DEFAULT_TEMPLATES_PATH));
+
+        let ugg_debug_dir = env::var("UGG_DEBUG_DIR")
+            .ok()
+            .filter(|dir| !dir.trim().is_empty());
+
+        let scraper_concurrency = env::var("SCRAPER_CONCURRENCY")
+            .ok()
+            .and_then(|concurrency| concurrency.trim().parse().ok())
+            .unwrap_or(DEFAULT_SCRAPER_CONCURRENCY);
+
+        let scraper_player_timeout_second = env::var("SCRAPER_PLAYER_TIMEOUT_SECOND")
+            .ok()
+            .and_then(|timeout| timeout.trim().parse().ok())
+            .unwrap_or(DEFAULT_SCRAPER_PLAYER_TIMEOUT_SECOND);
+
+        let scraper_retries = env::var("SCRAPER_RETRIES")
+            .ok()
+            .and_then(|retries| retries.trim().parse().ok())
+            .unwrap_or(DEFAULT_SCRAPER_RETRIES);
+
+        let scraper_retry_base_delay_ms = env::var("SCRAPER_RETRY_BASE_DELAY_MS")
+            .ok()
+            .and_then(|delay| delay.trim().parse().ok())
+            .unwrap_or(DEFAULT_SCRAPER_RETRY_BASE_DELAY_MS);
+
+        let circuit_breaker_threshold = env::var("CIRCUIT_BREAKER_THRESHOLD")
+            .ok()
+            .and_then(|threshold| threshold.trim().parse().ok())
+            .unwrap_or(DEFAULT_CIRCUIT_BREAKER_THRESHOLD);
+
+        let circuit_breaker_cooldown_minute = env::var("CIRCUIT_BREAKER_COOLDOWN_MINUTE")
+            .ok()
+            .and_then(|cooldown| cooldown.trim().parse().ok())
+            .unwrap_or(DEFAULT_CIRCUIT_BREAKER_COOLDOWN_MINUTE);
+
         Config {
             discord_token,
             discord_server_id,
+            default_guild_id,
             http_port,
             admin_api_key,
             database_path,
+            database_backend,
+            database_import_path,
+            database_backup_count,
             cron_interval_minute,
             skip_cron,
+            register_trial_fetch,
+            history_retention_day,
+            leaderboard_min_matches,
+            templates_path,
+            stats_source,
+            ugg_api_url,
+            fixture_dir,
+            ugg_debug_dir,
+            scraper_concurrency,
+            scraper_player_timeout_second,
+            scraper_retries,
+            scraper_retry_base_delay_ms,
+            circuit_breaker_threshold,
+            circuit_breaker_cooldown_minute,
+        }
+    }
+
+    pub fn history_retention(&self) -> Option<TimeDelta> {
+        match self.history_retention_day {
+            0 => None,
+            days => Some(TimeDelta::days(days as i64)),
         }
     }
 }
+
+/// `sqlite://rebot.sqlite` or a `.sqlite`/`.db` extension selects SQLite, anything else is JSON.
+fn parse_database_path(database_path: &str) -> (String, DatabaseBackend) {
+    if let Some(path) = database_path.strip_prefix("sqlite://") {
+        return (String::from(path), DatabaseBackend::Sqlite);
+    }
+    if let Some(path) = database_path.strip_prefix("json://") {
+        return (String::from(path), DatabaseBackend::Json);
+    }
+
+    let is_sqlite = [".sqlite", ".sqlite3", ".db"]
+        .iter()
+        .any(|extension| database_path.ends_with(extension));
+    let backend = if is_sqlite {
+        DatabaseBackend::Sqlite
+    } else {
+        DatabaseBackend::Json
+    };
+
+    (String::from(database_path), backend)
+}
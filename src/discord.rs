@@ -1,21 +1,43 @@
-use std::{
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::{sync::Arc, time::Duration};
 
 use log::{debug, error, info};
-use poise::serenity_prelude::{self as serenity, GuildId};
+use poise::{
+    ChoiceParameter,
+    serenity_prelude::{self as serenity, GuildId},
+};
 use serenity::all::GatewayIntents;
-use tokio::{sync::Mutex, sync::RwLock, time::timeout};
+use tokio::{sync::RwLock, time::timeout};
 
-use crate::player_store::{PlayerStore, RegisterError};
+use crate::{
+    locale::Locale,
+    model::rematch_profile::{Platform, ProfileError, RematchProfile},
+    player_store::{
+        AccountsDisplay, LeaderboardSort, PlayerRefresh, PlayerStore, PlayerWithStats,
+        RankAnnouncement, RankChange, RefreshReport, RegisterError, RegisteredPlayer,
+        SharedPlayerStore,
+    },
+    refresh_job::{RefreshJobs, RefreshState},
+    scraper::ScrapeError,
+    stats_source::{StatsSource, archive::PayloadArchive},
+    templates::{Messages, Templates},
+};
 
 struct DiscordState {
-    pub player_store: Arc<Mutex<PlayerStore>>,
+    pub player_store: SharedPlayerStore,
+    pub stats_source: Arc<dyn StatsSource>,
+    pub refresh_jobs: Arc<RefreshJobs>,
+    pub templates: Arc<Templates>,
 }
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, DiscordState, Error>;
 
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+const PAGINATION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const LEADERBOARD_PAGE_SIZE: usize = 10;
+const EMBED_COLOR: u32 = 0x2ecc71;
+/// Discord rate limits message edits, the refresh progress is shown at most this often
+const PROGRESS_EDIT_INTERVAL: Duration = Duration::from_secs(2);
+
 use songbird::{Event, EventContext, EventHandler, SerenityInit, TrackEvent, input::File};
 
 struct TrackEndNotifier {
@@ -32,19 +54,25 @@ impl EventHandler for TrackEndNotifier {
     }
 }
 
-#[poise::command(slash_command)]
+/// Plays a little something in your voice channel
+#[poise::command(
+    slash_command,
+    description_localized("fr", "Joue un petit quelque chose dans ton salon vocal")
+)]
 async fn marius(ctx: Context<'_>) -> Result<(), Error> {
+    let messages = get_messages(ctx).await;
     let context = ctx.serenity_context();
     let user_id = ctx.author().id;
     let guild_id = ctx.guild_id().unwrap();
 
-    play_marius(context, guild_id, user_id).await
+    play_marius(context, guild_id, user_id, messages.locale).await
 }
 
 pub async fn play_marius(
     serenity_context: &serenity::prelude::Context,
     guild_id: serenity::GuildId,
     user_id: serenity::UserId,
+    locale: Locale,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     info!("guild_id={}", guild_id);
     info!("user_id={}", user_id);
@@ -54,7 +82,7 @@ pub async fn play_marius(
         let voice_state = guild
             .voice_states
             .get(&user_id)
-            .ok_or("Rejoins le serveur vocal avant d'exécuter cette commande ! 😉")?;
+            .ok_or_else(|| locale.message("marius.not_in_voice", &[]))?;
         voice_state
             .channel_id
             .ok_or("No channel ID found in voice state")?
@@ -88,133 +116,1151 @@ pub async fn play_marius(
     Ok(())
 }
 
-#[poise::command(slash_command)]
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+enum PlatformChoice {
+    #[name = "Steam"]
+    Steam,
+    #[name = "Xbox"]
+    Xbox,
+    #[name = "PlayStation"]
+    Psn,
+    #[name = "Epic Games"]
+    Epic,
+}
+
+impl PlatformChoice {
+    fn platform(&self) -> Platform {
+        match self {
+            PlatformChoice::Steam => Platform::Steam,
+            PlatformChoice::Xbox => Platform::Xbox,
+            PlatformChoice::Psn => Platform::Psn,
+            PlatformChoice::Epic => Platform::Epic,
+        }
+    }
+}
+
+/// Registers your u.gg page, from its URL or from your platform and id
+#[poise::command(
+    slash_command,
+    name_localized("fr", "inscription"),
+    guild_only,
+    description_localized(
+        "fr",
+        "Enregistre ta page u.gg, depuis son URL ou depuis ta plateforme et ton identifiant"
+    )
+)]
 async fn register(
     ctx: Context<'_>,
-    #[description = "URL rematch"] rematch_url: String,
+    #[description = "u.gg Rematch URL"]
+    #[description_localized("fr", "URL rematch")]
+    #[name_localized("fr", "url_rematch")]
+    rematch_url: Option<String>,
+    #[description = "Platform, instead of the URL"]
+    #[description_localized("fr", "Plateforme, à la place de l'URL")]
+    #[name_localized("fr", "plateforme")]
+    platform: Option<PlatformChoice>,
+    #[description = "Platform account id, instead of the URL"]
+    #[description_localized("fr", "Identifiant du compte sur la plateforme, à la place de l'URL")]
+    #[name_localized("fr", "identifiant")]
+    id: Option<String>,
+    #[description = "Name on the platform, along with the id"]
+    #[description_localized("fr", "Nom sur la plateforme, avec l'identifiant")]
+    #[name_localized("fr", "nom")]
+    name: Option<String>,
+    #[description = "Account name, e.g. smurf"]
+    #[description_localized("fr", "Nom du compte, par exemple smurf")]
+    #[name_localized("fr", "étiquette")]
+    label: Option<String>,
+    #[description = "Show this account by default"]
+    #[description_localized("fr", "Afficher ce compte par défaut")]
+    #[name_localized("fr", "principal")]
+    main: Option<bool>,
 ) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    let messages = get_messages(ctx).await;
     let u = ctx.author();
     info!(
-        "Register command for user id={} with url={}",
-        u.id, rematch_url
+        "Register command for user id={} in guild id={} with url={:?} platform={:?} id={:?} name={:?} label={:?}",
+        u.id, guild_id, rematch_url, platform, id, name, label
     );
 
-    let mut player_store = ctx.data().player_store.lock().await;
-    let register_status = player_store.register_player(u.id.into(), &rematch_url);
+    // u.gg finds a profile from its platform and id, the name is only there for humans
+    let rematch_url = match (rematch_url, platform, id, name) {
+        (Some(rematch_url), None, None, None) => Ok(rematch_url),
+        (Some(_), _, _, _) => Err("register.conflicting_profile"),
+        (None, Some(platform), Some(id), name) => {
+            let platform_id = id.trim().to_owned();
+            let display_name = name
+                .map(|name| name.trim().to_owned())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| platform_id.clone());
+            Ok(RematchProfile {
+                platform: platform.platform(),
+                display_name,
+                platform_id,
+            }
+            .to_url())
+        }
+        (None, _, None, Some(_)) => Err("register.name_only"),
+        _ => Err("register.missing_profile"),
+    };
+    let rematch_url = match rematch_url {
+        Ok(rematch_url) => rematch_url,
+        Err(key) => {
+            let response = messages.get(key, &[("user", &u.name)]);
+            ctx.say(response).await?;
+            return Ok(());
+        }
+    };
 
-    let response = match register_status {
-        Ok(_) => format!("Très bien {}, on a enregistré ta page u.gg !", u.name),
-        Err(RegisterError::WrongUrl(e)) => format!("Fais un effort {}, {}", u.name, e),
+    let (profile, trial_fetch) = {
+        let player_store = ctx.data().player_store.read().await;
+        (
+            player_store.check_profile(u.id.into(), &rematch_url),
+            player_store.config.register_trial_fetch,
+        )
     };
-    debug!(
-        "Now there is {} players in the store {:?}",
-        player_store.registered_players.len(),
-        player_store.registered_players
+
+    // Making sure u.gg knows the profile before saving it
+    let mut unchecked = false;
+    if let (Ok(profile), true) = (&profile, trial_fetch) {
+        ctx.defer().await?;
+        let trial_player = RegisteredPlayer {
+            guild_id: guild_id.into(),
+            discord_id: u.id.into(),
+            rematch_url: profile.to_url(),
+            label: None,
+            main: false,
+        };
+
+        match ctx
+            .data()
+            .stats_source
+            .get_player_stats(&trial_player)
+            .await
+        {
+            Ok(_) => {}
+            Err(e @ (ScrapeError::ProfileNotFound | ScrapeError::Parse(_))) => {
+                let response = messages.get(
+                    "register.unknown_profile",
+                    &[
+                        ("user", &u.name),
+                        ("error", &compute_pretty_scrape_error(&e, messages)),
+                    ],
+                );
+                ctx.say(response).await?;
+                return Ok(());
+            }
+            Err(e) => {
+                info!("Registering {} without trial fetch, {:?}", rematch_url, e);
+                unchecked = true;
+            }
+        }
+    }
+
+    let register_status = {
+        let mut player_store = ctx.data().player_store.write().await;
+        let register_status = player_store.register_player(
+            guild_id.into(),
+            u.id.into(),
+            &rematch_url,
+            label.as_deref(),
+            main.unwrap_or(false),
+        );
+        debug!(
+            "Now there is {} players in the store {:?}",
+            player_store.registered_players.len(),
+            player_store.registered_players
+        );
+        register_status
+    };
+
+    let response = match (register_status, profile) {
+        (Ok(_), Ok(profile)) => messages.get(
+            if unchecked {
+                "register.done_unchecked"
+            } else {
+                "register.done"
+            },
+            &[("user", &u.name), ("name", &profile.display_name)],
+        ),
+        (Ok(_), Err(_)) => messages.get("register.done_without_name", &[("user", &u.name)]),
+        (Err(RegisterError::WrongUrl(e)), _) => messages.get(
+            "register.wrong_url",
+            &[
+                ("user", &u.name),
+                ("error", &compute_pretty_profile_error(&e, messages)),
+            ],
+        ),
+        (Err(RegisterError::AlreadyRegistered(discord_id)), _) => messages.get(
+            "register.already_registered",
+            &[("user", &u.name), ("owner", &format!("<@{}>", discord_id))],
+        ),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(response)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
+    Ok(())
+}
+
+fn compute_pretty_profile_error(error: &ProfileError, messages: Messages) -> String {
+    match error {
+        ProfileError::Url(_) | ProfileError::NotAProfile => messages.get(
+            "profile_error.url",
+            &[(
+                "example",
+                &"https://u.gg/rematch/profile/steam/La%20m%C3%A9sange%20du%20Val%20d'Oise/76561198355389674",
+            )],
+        ),
+        ProfileError::UnknownPlatform(platform) => {
+            messages.get("profile_error.unknown_platform", &[("platform", platform)])
+        }
+        ProfileError::MissingPlatformId => messages.get("profile_error.missing_platform_id", &[]),
+    }
+}
+
+/// Removes your accounts from this server
+#[poise::command(
+    slash_command,
+    name_localized("fr", "désinscription"),
+    guild_only,
+    description_localized("fr", "Supprime tes comptes de ce serveur")
+)]
+async fn unregister(
+    ctx: Context<'_>,
+    #[description = "Account name, all of them otherwise"]
+    #[description_localized("fr", "Nom du compte, tous sinon")]
+    #[name_localized("fr", "compte")]
+    account: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    let messages = get_messages(ctx).await;
+    let u = ctx.author();
+    info!(
+        "Unregister command for user id={} in guild id={} with account={:?}",
+        u.id, guild_id, account
     );
-    ctx.say(response).await?;
+
+    let question = match &account {
+        Some(account) => messages.get("unregister.confirm_account", &[("account", account)]),
+        None => messages.get("unregister.confirm_all", &[]),
+    };
+    let Some(interaction) = confirm(ctx, messages, question).await? else {
+        return Ok(());
+    };
+
+    let (removed, ugg_debug_dir) = {
+        let mut player_store = ctx.data().player_store.write().await;
+        (
+            player_store.unregister_player(guild_id.into(), u.id.into(), account.as_deref()),
+            player_store.config.ugg_debug_dir.clone(),
+        )
+    };
+    PayloadArchive::new(ugg_debug_dir.as_deref())
+        .remove(&removed.forgotten_profiles)
+        .await;
+
+    let response = match removed.count {
+        0 => messages.get("unregister.nothing", &[]),
+        _ => messages.get("unregister.done", &[("user", &u.name)]),
+    };
+    answer_confirmation(ctx, &interaction, response).await
+}
+
+/// Same as /unregister, for another member
+#[poise::command(
+    slash_command,
+    name_localized("fr", "désinscrire_joueur"),
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    description_localized("fr", "Comme /unregister, pour un autre membre")
+)]
+async fn unregister_player(
+    ctx: Context<'_>,
+    #[description = "Selected user"]
+    #[description_localized("fr", "Membre choisi")]
+    #[name_localized("fr", "membre")]
+    user: serenity::User,
+    #[description = "Account name, all of them otherwise"]
+    #[description_localized("fr", "Nom du compte, tous sinon")]
+    #[name_localized("fr", "compte")]
+    account: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    let messages = get_messages(ctx).await;
+    info!(
+        "Unregister player command for author id={}, target user id={} in guild id={} with account={:?}",
+        ctx.author().id,
+        user.id,
+        guild_id,
+        account
+    );
+
+    let question = match &account {
+        Some(account) => messages.get(
+            "unregister_player.confirm_account",
+            &[("account", account), ("user", &user.name)],
+        ),
+        None => messages.get("unregister_player.confirm_all", &[("user", &user.name)]),
+    };
+    let Some(interaction) = confirm(ctx, messages, question).await? else {
+        return Ok(());
+    };
+
+    let (removed, ugg_debug_dir) = {
+        let mut player_store = ctx.data().player_store.write().await;
+        (
+            player_store.unregister_player(guild_id.into(), user.id.into(), account.as_deref()),
+            player_store.config.ugg_debug_dir.clone(),
+        )
+    };
+    PayloadArchive::new(ugg_debug_dir.as_deref())
+        .remove(&removed.forgotten_profiles)
+        .await;
+
+    let response = match removed.count {
+        0 => messages.get("unregister_player.nothing", &[("user", &user.name)]),
+        _ => messages.get("unregister_player.done", &[("user", &user.name)]),
+    };
+    answer_confirmation(ctx, &interaction, response).await
+}
+
+/// Removes all your accounts, stats and history from every server
+#[poise::command(
+    slash_command,
+    name_localized("fr", "oublie_moi"),
+    description_localized(
+        "fr",
+        "Supprime tous tes comptes, tes stats et ton historique de tous les serveurs"
+    )
+)]
+async fn forgetme(ctx: Context<'_>) -> Result<(), Error> {
+    let messages = get_messages(ctx).await;
+    let u = ctx.author();
+    info!("Forgetme command for user id={}", u.id);
+
+    let question = messages.get("forgetme.confirm", &[]);
+    let Some(interaction) = confirm(ctx, messages, question).await? else {
+        return Ok(());
+    };
+
+    let (removed, ugg_debug_dir) = {
+        let mut player_store = ctx.data().player_store.write().await;
+        (
+            player_store.forget_player(u.id.into()),
+            player_store.config.ugg_debug_dir.clone(),
+        )
+    };
+    PayloadArchive::new(ugg_debug_dir.as_deref())
+        .remove(&removed.forgotten_profiles)
+        .await;
+
+    let response = match removed.count {
+        0 => messages.get("forgetme.nothing", &[]),
+        _ => messages.get("forgetme.done", &[("user", &u.name)]),
+    };
+    answer_confirmation(ctx, &interaction, response).await
+}
+
+/// Asks the author to confirm with a button, `None` when they cancel or do not answer in time.
+async fn confirm(
+    ctx: Context<'_>,
+    messages: Messages<'_>,
+    question: String,
+) -> Result<Option<serenity::ComponentInteraction>, Error> {
+    let confirm_id = format!("{}-confirm", ctx.id());
+    let cancel_id = format!("{}-cancel", ctx.id());
+
+    let buttons = serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(&confirm_id)
+            .style(serenity::ButtonStyle::Danger)
+            .label(messages.get("confirm.delete", &[])),
+        serenity::CreateButton::new(&cancel_id)
+            .style(serenity::ButtonStyle::Secondary)
+            .label(messages.get("confirm.cancel", &[])),
+    ]);
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(question)
+                .ephemeral(true)
+                .components(vec![buttons]),
+        )
+        .await?;
+
+    let collector_ids = (confirm_id.clone(), cancel_id);
+    let interaction = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .channel_id(ctx.channel_id())
+        .timeout(CONFIRMATION_TIMEOUT)
+        .filter(move |i| i.data.custom_id == collector_ids.0 || i.data.custom_id == collector_ids.1)
+        .await;
+
+    match interaction {
+        Some(interaction) if interaction.data.custom_id == confirm_id => Ok(Some(interaction)),
+        Some(interaction) => {
+            let response = messages.get("confirm.cancelled", &[]);
+            answer_confirmation(ctx, &interaction, response).await?;
+            Ok(None)
+        }
+        None => {
+            reply
+                .edit(
+                    ctx,
+                    poise::CreateReply::default()
+                        .content(messages.get("confirm.timeout", &[]))
+                        .components(vec![]),
+                )
+                .await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Replaces the confirmation question and its buttons with the outcome.
+async fn answer_confirmation(
+    ctx: Context<'_>,
+    interaction: &serenity::ComponentInteraction,
+    response: String,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(response)
+                    .components(vec![]),
+            ),
+        )
+        .await?;
     Ok(())
 }
 
-#[poise::command(slash_command)]
+/// Fetches the latest stats of every registered player
+#[poise::command(
+    slash_command,
+    name_localized("fr", "rafraîchir"),
+    guild_only,
+    description_localized("fr", "Récupère les dernières stats de tous les joueurs enregistrés")
+)]
 async fn refresh(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    let messages = get_messages(ctx).await;
     let u = ctx.author();
-    info!("Refresh command for user id={}", u.id);
+    info!(
+        "Refresh command for user id={} in guild id={}",
+        u.id, guild_id
+    );
+
+    let circuit_status = ctx.data().stats_source.circuit_status();
+    if let Some(retry_in_second) = circuit_status.and_then(|status| status.retry_in_second) {
+        let response = messages.get(
+            "refresh.circuit_open",
+            &[("minutes", &retry_in_second.div_ceil(60))],
+        );
+        ctx.say(response).await?;
+        return Ok(());
+    }
+
+    let mut job = ctx.data().refresh_jobs.start().await;
+    let response = if job.started {
+        messages.get("refresh.started", &[])
+    } else {
+        messages.get("refresh.joined", &[])
+    };
+    let reply = ctx.say(&response).await?;
+
+    let mut state = Some(job.state());
+    let report = loop {
+        match state {
+            Some(RefreshState::Running { done, total }) => {
+                let progress = format!(
+                    "{}\n{}",
+                    response,
+                    messages.get("refresh.progress", &[("done", &done), ("total", &total)])
+                );
+                if let Err(e) = reply
+                    .edit(ctx, poise::CreateReply::default().content(progress))
+                    .await
+                {
+                    debug!("Could not show refresh progress, {}", e);
+                }
+            }
+            Some(RefreshState::Done(report)) => break report,
+            None => {
+                reply
+                    .edit(
+                        ctx,
+                        poise::CreateReply::default().content(messages.get("refresh.stopped", &[])),
+                    )
+                    .await?;
+                return Ok(());
+            }
+        }
+        tokio::time::sleep(PROGRESS_EDIT_INTERVAL).await;
+        state = job.next_state().await;
+    };
+
+    let (response, announcements) = {
+        let player_store = ctx.data().player_store.read().await;
+        let response = compute_refresh_summary(&player_store, guild_id.into(), &report, messages);
+        let announcements = player_store.get_rank_change_announcements(&report.rank_changes);
+        (response, announcements)
+    };
+    reply
+        .edit(
+            ctx,
+            poise::CreateReply::default()
+                .content(response)
+                .allowed_mentions(serenity::CreateAllowedMentions::new()),
+        )
+        .await?;
+
+    // Whoever started the refresh announces it, so that joining it does not announce twice
+    if job.started {
+        announce_rank_changes(ctx.http(), &ctx.data().templates, &announcements).await;
+    }
+
+    Ok(())
+}
+
+/// Posts each rank change in the announcement channel it is paired with.
+pub async fn announce_rank_changes(
+    http: &serenity::Http,
+    templates: &Templates,
+    announcements: &[RankAnnouncement],
+) {
+    for announcement in announcements {
+        let messages = templates.messages(announcement.locale);
+        let message = compute_rank_change_message(&announcement.rank_change, messages);
+        let channel = serenity::ChannelId::new(announcement.channel_id);
+        if let Err(e) = channel.say(http, &message).await {
+            error!(
+                "Could not announce rank change in {}, {}",
+                announcement.channel_id, e
+            );
+        }
+    }
+}
+
+fn compute_rank_change_message(rank_change: &RankChange, messages: Messages) -> String {
+    let player = compute_pretty_player_name(&rank_change.display_name);
+    let rank = rank_change.to.value().name(messages);
+
+    let key = match (&rank_change.from, rank_change.is_promotion()) {
+        (None, _) => "rank_change.first",
+        (Some(_), true) => "rank_change.promotion",
+        (Some(_), false) => "rank_change.demotion",
+    };
+    messages.get(key, &[("player", &player), ("rank", &rank)])
+}
+
+/// Where rank promotions and demotions are posted, leave empty to stop announcing them
+#[poise::command(
+    slash_command,
+    name_localized("fr", "annonces"),
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    description_localized(
+        "fr",
+        "Où annoncer les promotions et les rétrogradations, vide pour ne plus les annoncer"
+    )
+)]
+async fn announce(
+    ctx: Context<'_>,
+    #[description = "Announcement channel"]
+    #[description_localized("fr", "Salon des annonces")]
+    #[name_localized("fr", "salon")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    let messages = get_messages(ctx).await;
+    info!(
+        "Announce command for guild id={} with channel={:?}",
+        guild_id,
+        channel.as_ref().map(|c| c.id)
+    );
 
-    let response = format!(
-        "On démarre le scraping intensif, ça peut prendre quelques secondes. SVP u.gg ne portez pas plainte !"
+    ctx.data()
+        .player_store
+        .write()
+        .await
+        .set_announcement_channel(guild_id.into(), channel.as_ref().map(|c| c.id.into()));
+
+    let response = match channel {
+        Some(channel) => messages.get(
+            "announce.set",
+            &[("channel", &format!("<#{}>", channel.id))],
+        ),
+        None => messages.get("announce.unset", &[]),
+    };
+    ctx.say(response).await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+enum LeaderboardAccounts {
+    #[name = "Added up"]
+    #[name_localized("fr", "Cumulés")]
+    Aggregate,
+    #[name = "Separate"]
+    #[name_localized("fr", "Séparés")]
+    List,
+}
+
+/// How /stats shows users with several accounts
+#[poise::command(
+    slash_command,
+    name_localized("fr", "comptes"),
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    description_localized("fr", "Comment /stats affiche les membres ayant plusieurs comptes")
+)]
+async fn accounts(
+    ctx: Context<'_>,
+    #[description = "Accounts of a user"]
+    #[description_localized("fr", "Comptes d'un membre")]
+    #[name_localized("fr", "affichage")]
+    display: LeaderboardAccounts,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    let messages = get_messages(ctx).await;
+    info!(
+        "Accounts command for guild id={} with display={:?}",
+        guild_id, display
     );
+
+    let accounts_display = match display {
+        LeaderboardAccounts::Aggregate => AccountsDisplay::Aggregate,
+        LeaderboardAccounts::List => AccountsDisplay::List,
+    };
+
+    ctx.data()
+        .player_store
+        .write()
+        .await
+        .set_accounts_display(guild_id.into(), accounts_display);
+
+    let response = match accounts_display {
+        AccountsDisplay::Aggregate => messages.get("accounts.aggregate", &[]),
+        AccountsDisplay::List => messages.get("accounts.list", &[]),
+    };
     ctx.say(response).await?;
+    Ok(())
+}
 
-    let mut player_store = ctx.data().player_store.lock().await;
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+enum LanguageChoice {
+    #[name = "Français"]
+    Fr,
+    #[name = "English"]
+    En,
+}
 
-    let now = Instant::now();
-    if let Err(_) = player_store.refresh_all().await {
-        error!("Could not refresh");
+impl LanguageChoice {
+    fn locale(&self) -> Locale {
+        match self {
+            LanguageChoice::Fr => Locale::Fr,
+            LanguageChoice::En => Locale::En,
+        }
     }
+}
 
-    player_store.print();
+/// Language the bot answers you in, leave empty to use the one of each server
+#[poise::command(
+    slash_command,
+    name_localized("fr", "langue"),
+    description_localized(
+        "fr",
+        "Langue dans laquelle le bot te répond, vide pour celle de chaque serveur"
+    )
+)]
+async fn language(
+    ctx: Context<'_>,
+    #[description = "Your language"]
+    #[description_localized("fr", "Ta langue")]
+    #[name_localized("fr", "langue")]
+    language: Option<LanguageChoice>,
+) -> Result<(), Error> {
+    let u = ctx.author();
+    info!(
+        "Language command for user id={} with language={:?}",
+        u.id, language
+    );
 
-    info!("Players refresh success in {}s", now.elapsed().as_secs());
+    let locale = language.map(|language| language.locale());
+    ctx.data()
+        .player_store
+        .write()
+        .await
+        .set_user_locale(u.id.into(), locale);
+
+    let response = match locale {
+        Some(locale) => ctx
+            .data()
+            .templates
+            .messages(locale)
+            .get("language.user", &[]),
+        None => get_messages(ctx).await.get("language.user_reset", &[]),
+    };
+    ctx.say(response).await?;
+    Ok(())
+}
+
+/// Language the bot answers in on this server, members can pick their own with /language
+#[poise::command(
+    slash_command,
+    name_localized("fr", "langue_serveur"),
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    description_localized(
+        "fr",
+        "Langue du bot sur ce serveur, chacun peut choisir la sienne avec /language"
+    )
+)]
+async fn server_language(
+    ctx: Context<'_>,
+    #[description = "Server language"]
+    #[description_localized("fr", "Langue du serveur")]
+    #[name_localized("fr", "langue")]
+    language: LanguageChoice,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    info!(
+        "Server language command for guild id={} with language={:?}",
+        guild_id, language
+    );
 
+    let locale = language.locale();
+    ctx.data()
+        .player_store
+        .write()
+        .await
+        .set_guild_locale(guild_id.into(), locale);
+
+    ctx.say(
+        ctx.data()
+            .templates
+            .messages(locale)
+            .get("language.guild", &[]),
+    )
+    .await?;
     Ok(())
 }
 
-#[poise::command(slash_command)]
+/// Only tells about the players registered in the guild, other guilds are none of its business.
+fn compute_refresh_summary(
+    player_store: &PlayerStore,
+    guild_id: u64,
+    report: &RefreshReport,
+    messages: Messages<'_>,
+) -> String {
+    let in_guild = |refresh: &PlayerRefresh| {
+        player_store.is_profile_registered(guild_id, &refresh.rematch_url)
+    };
+    let success_count = report
+        .players
+        .iter()
+        .filter(|p| p.result.is_ok() && in_guild(p))
+        .count();
+    let failures: Vec<_> = report
+        .failures()
+        .into_iter()
+        .filter(|(refresh, _)| in_guild(refresh))
+        .collect();
+    let summary = messages.get(
+        "refresh.summary",
+        &[
+            ("ok", &success_count),
+            ("failed", &failures.len()),
+            ("seconds", &report.duration.as_secs()),
+        ],
+    );
+
+    if failures.is_empty() {
+        return summary;
+    }
+
+    let details: Vec<String> = failures
+        .iter()
+        .map(|(refresh, e)| {
+            let player = match &refresh.display_name {
+                Some(display_name) => compute_pretty_player_name(display_name),
+                None => format!("<@{}>", refresh.discord_id),
+            };
+            format!("{} {}", player, compute_pretty_scrape_error(e, messages))
+        })
+        .collect();
+
+    messages.get(
+        "refresh.summary_failures",
+        &[("summary", &summary), ("failures", &details.join(", "))],
+    )
+}
+
+fn compute_pretty_scrape_error(error: &ScrapeError, messages: Messages) -> String {
+    let key = match error {
+        ScrapeError::PageInit(_) => "scrape_error.page_init",
+        ScrapeError::RequestNotFound => "scrape_error.request_not_found",
+        ScrapeError::ProfileNotFound => "scrape_error.not_found",
+        ScrapeError::RequestContent => "scrape_error.content",
+        ScrapeError::Timeout => "scrape_error.timeout",
+        ScrapeError::Parse(_) => "scrape_error.parse",
+        ScrapeError::CircuitOpen => "scrape_error.circuit_open",
+    };
+    messages.get(key, &[])
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+enum GameMode {
+    #[name = "All"]
+    #[name_localized("fr", "Tous")]
+    All,
+    #[name = "Ranked"]
+    #[name_localized("fr", "Classé")]
+    Ranked,
+    #[name = "Quick match"]
+    #[name_localized("fr", "Partie rapide")]
+    QuickMatch,
+}
+
+impl GameMode {
+    /// Name of the matching bucket in the u.gg lifetime stats
+    fn ugg_key(&self) -> &'static str {
+        match self {
+            GameMode::All => "All",
+            GameMode::Ranked => "Ranked",
+            GameMode::QuickMatch => "QuickMatch",
+        }
+    }
+}
+
+/// Shows the stats of a player
+#[poise::command(
+    slash_command,
+    guild_only,
+    description_localized("fr", "Affiche les stats d'un joueur")
+)]
 async fn stat(
     ctx: Context<'_>,
-    #[description = "Selected user"] user: Option<serenity::User>,
+    #[description = "Selected user"]
+    #[description_localized("fr", "Membre choisi")]
+    #[name_localized("fr", "membre")]
+    user: Option<serenity::User>,
+    #[description = "Game mode"]
+    #[description_localized("fr", "Mode de jeu")]
+    mode: Option<GameMode>,
+    #[description = "Account name, the main account otherwise"]
+    #[description_localized("fr", "Nom du compte, le compte principal sinon")]
+    #[name_localized("fr", "compte")]
+    account: Option<String>,
 ) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    let messages = get_messages(ctx).await;
     let u = user.as_ref().unwrap_or_else(|| ctx.author());
     info!(
-        "Stat command for author id={}, target user id={}",
+        "Stat command for author id={}, target user id={} in guild id={}",
         ctx.author().id,
-        u.id
+        u.id,
+        guild_id
     );
 
-    let player_store = ctx.data().player_store.lock().await;
-    let player_stat = player_store.get_player_stat(u.id.into());
+    let mode = mode.unwrap_or(GameMode::All);
 
-    let response = match player_stat {
-        None => format!(
-            "{} n'est pas enregistré, pense à utiliser la commande /register",
-            u.name
-        ),
-        Some(player) => format!(
-            "**{}** aussi connu sous le nom **{}** est rang **{}**, **{}**",
-            compute_pretty_player_name(&u.name),
-            player.display_name,
-            player.pretty_rank(),
-            player.get_pretty_stats()
+    // Cloned so that the store is not held while discord answers
+    let player_stat = {
+        let player_store = ctx.data().player_store.read().await;
+        match &account {
+            Some(account) => player_store.get_account_stat(guild_id.into(), u.id.into(), account),
+            None => player_store.get_player_stat(guild_id.into(), u.id.into()),
+        }
+        .cloned()
+    };
+
+    let response = match (player_stat, account) {
+        (None, Some(account)) => messages.get(
+            "stat.no_account",
+            &[("user", &u.name), ("account", &account)],
         ),
+        (None, None) => messages.get("stat.not_registered", &[("user", &u.name)]),
+        (Some(player), _) => match compute_stat_embed(&u.name, &player, mode, messages) {
+            Some(embed) => {
+                ctx.send(poise::CreateReply::default().embed(embed)).await?;
+                return Ok(());
+            }
+            None => messages.get(
+                "stat.never_played",
+                &[
+                    ("user", &compute_pretty_player_name(&u.name)),
+                    ("mode", &choice_name(&mode, messages.locale)),
+                ],
+            ),
+        },
     };
 
     ctx.say(response).await?;
     Ok(())
 }
 
-#[poise::command(slash_command)]
-async fn stats(ctx: Context<'_>) -> Result<(), Error> {
+/// `None` when the player never played the game mode.
+fn compute_stat_embed(
+    user_name: &str,
+    player: &PlayerWithStats,
+    mode: GameMode,
+    messages: Messages<'_>,
+) -> Option<serenity::CreateEmbed> {
+    let record = player.get_pretty_mode_record(mode.ugg_key(), messages)?;
+    let win_rate = player.get_mode_win_rate(mode.ugg_key())?;
+    let counters = player.get_pretty_counters(mode.ugg_key(), messages)?;
+
+    let last_refresh = match player.last_refreshed_at {
+        Some(date) => format!(
+            "<t:{}:R>{}",
+            date.timestamp(),
+            player.pretty_freshness(messages)
+        ),
+        None => messages.get("stat.never_refreshed", &[]),
+    };
+    let hours = messages.get("stat.hours", &[("hours", &player.estimate_hours_played())]);
+
+    let embed = serenity::CreateEmbed::new()
+        .title(messages.get(
+            "stat.title",
+            &[
+                ("user", &compute_pretty_player_name(user_name)),
+                ("name", &player.display_name),
+            ],
+        ))
+        .color(EMBED_COLOR)
+        .field(
+            messages.get("stat.rank", &[]),
+            player.rank_value().name(messages),
+            true,
+        )
+        .field(messages.get("stat.record", &[]), record, true)
+        .field(
+            messages.get("stat.win_rate", &[]),
+            format!("{}%", win_rate),
+            true,
+        )
+        .field(
+            messages.get("stat.level", &[]),
+            player.level.to_string(),
+            true,
+        )
+        .field(messages.get("stat.play_time", &[]), hours, true)
+        .field(messages.get("stat.last_refresh", &[]), last_refresh, true)
+        .field(choice_name(&mode, messages.locale), counters, false);
+
+    Some(embed)
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+enum SortChoice {
+    #[name = "Rank"]
+    #[name_localized("fr", "Rang")]
+    Rank,
+    #[name = "Win rate"]
+    #[name_localized("fr", "Taux de victoire")]
+    WinRate,
+    #[name = "Matches played"]
+    #[name_localized("fr", "Parties jouées")]
+    Matches,
+    #[name = "Wins"]
+    #[name_localized("fr", "Victoires")]
+    Wins,
+    #[name = "Level"]
+    #[name_localized("fr", "Niveau")]
+    Level,
+}
+
+impl SortChoice {
+    fn leaderboard_sort(&self) -> LeaderboardSort {
+        match self {
+            SortChoice::Rank => LeaderboardSort::Rank,
+            SortChoice::WinRate => LeaderboardSort::WinRate,
+            SortChoice::Matches => LeaderboardSort::Matches,
+            SortChoice::Wins => LeaderboardSort::Wins,
+            SortChoice::Level => LeaderboardSort::Level,
+        }
+    }
+}
+
+/// Shows the leaderboard of this server
+#[poise::command(
+    slash_command,
+    name_localized("fr", "classement"),
+    guild_only,
+    description_localized("fr", "Affiche le classement de ce serveur")
+)]
+async fn stats(
+    ctx: Context<'_>,
+    #[description = "Leaderboard order, by rank otherwise"]
+    #[description_localized("fr", "Ordre du classement, par rang sinon")]
+    #[name_localized("fr", "tri")]
+    sort: Option<SortChoice>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Guild only command")?;
+    let messages = get_messages(ctx).await;
     let u = ctx.author();
     info!(
-        "Stats command for author id={}, target user id={}",
+        "Stats command for author id={}, target user id={} in guild id={} sorted by {:?}",
         ctx.author().id,
-        u.id
+        u.id,
+        guild_id,
+        sort
     );
 
-    let player_store = ctx.data().player_store.lock().await;
-    let all_players = player_store.get_all_players_stat();
+    let sort = sort.map(|sort| sort.leaderboard_sort()).unwrap_or_default();
+
+    let player_store = ctx.data().player_store.read().await;
+    let all_players = player_store.get_leaderboard(guild_id.into(), sort);
 
-    let stats: Vec<String> = all_players
+    let lines: Vec<String> = all_players
         .iter()
-        .map(|player| {
+        .enumerate()
+        .map(|(i, player)| {
             format!(
-                "* {} - {} - {}",
+                "{}. **{}** - {} - {}{}",
+                i + 1,
                 compute_pretty_player_name(&player.display_name),
-                player.pretty_rank(),
-                player.get_pretty_stats()
+                player.rank_value().name(messages),
+                player.get_pretty_stats(messages),
+                player.pretty_freshness(messages)
             )
         })
         .collect();
-    let players_pretty_stat = stats.join("\n");
 
-    let try_hard_player = player_store.find_try_harder();
+    let try_hard_player = player_store.find_try_harder(guild_id.into());
+    // Paging can last a while, nobody else should wait on it
+    drop(player_store);
 
-    let response = match try_hard_player {
-        None => players_pretty_stat,
-        Some(p) => {
-            let player = compute_pretty_player_name(&p.display_name);
-            let hours_played = p.estimate_hours_played();
-            format!(
-                "{}\nEt la palme d'or du plus gros try harder revient à {} avec plus de {}h de jeu cette saison !",
-                players_pretty_stat, player, hours_played,
-            )
-        }
+    if lines.is_empty() {
+        ctx.say(messages.get("stats.empty", &[])).await?;
+        return Ok(());
+    }
+
+    let try_hard = try_hard_player.map(|p| {
+        messages.get(
+            "stats.try_harder",
+            &[
+                ("player", &compute_pretty_player_name(&p.display_name)),
+                ("hours", &p.estimate_hours_played()),
+            ],
+        )
+    });
+
+    let pages: Vec<&[String]> = lines.chunks(LEADERBOARD_PAGE_SIZE).collect();
+    let embeds: Vec<serenity::CreateEmbed> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| {
+            let mut embed = serenity::CreateEmbed::new()
+                .title(messages.get("stats.title", &[]))
+                .color(EMBED_COLOR)
+                .description(page.join("\n"));
+            if pages.len() > 1 {
+                embed = embed.footer(serenity::CreateEmbedFooter::new(
+                    messages.get("stats.page", &[("page", &(i + 1)), ("pages", &pages.len())]),
+                ));
+            }
+            if let Some(try_hard) = &try_hard {
+                embed = embed.field(messages.get("stats.try_harder_title", &[]), try_hard, false);
+            }
+            embed
+        })
+        .collect();
+
+    paginate(ctx, embeds).await
+}
+
+/// Shows the first embed with previous/next buttons to browse the others, until nobody
+/// used them for a while.
+async fn paginate(ctx: Context<'_>, embeds: Vec<serenity::CreateEmbed>) -> Result<(), Error> {
+    let previous_id = format!("{}-previous", ctx.id());
+    let next_id = format!("{}-next", ctx.id());
+
+    let buttons = |page: usize| {
+        vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(&previous_id)
+                .emoji('◀')
+                .disabled(page == 0),
+            serenity::CreateButton::new(&next_id)
+                .emoji('▶')
+                .disabled(page + 1 == embeds.len()),
+        ])]
     };
 
-    ctx.say(response).await?;
+    let mut page = 0;
+    let mut reply = poise::CreateReply::default().embed(embeds[page].clone());
+    if embeds.len() > 1 {
+        reply = reply.components(buttons(page));
+    }
+    let reply = ctx.send(reply).await?;
+    if embeds.len() <= 1 {
+        return Ok(());
+    }
+
+    let ctx_id = ctx.id();
+    while let Some(press) = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(PAGINATION_TIMEOUT)
+        .await
+    {
+        if press.data.custom_id == next_id {
+            page = (page + 1).min(embeds.len() - 1);
+        } else if press.data.custom_id == previous_id {
+            page = page.saturating_sub(1);
+        } else {
+            continue;
+        }
+
+        press
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .embed(embeds[page].clone())
+                        .components(buttons(page)),
+                ),
+            )
+            .await?;
+    }
+
+    reply
+        .edit(
+            ctx,
+            poise::CreateReply::default()
+                .embed(embeds[page].clone())
+                .components(vec![]),
+        )
+        .await?;
     Ok(())
 }
 
+/// Messages in the language of the author, to read before holding the store.
+async fn get_messages(ctx: Context<'_>) -> Messages<'_> {
+    let guild_id = ctx.guild_id().map(u64::from);
+    let locale = ctx
+        .data()
+        .player_store
+        .read()
+        .await
+        .get_locale(guild_id, ctx.author().id.into());
+
+    ctx.data().templates.messages(locale)
+}
+
+/// Name of a command option choice as the members of that locale see it.
+fn choice_name<T: ChoiceParameter>(choice: &T, locale: Locale) -> &'static str {
+    choice
+        .localized_name(locale.code())
+        .unwrap_or_else(|| choice.name())
+}
+
 fn compute_pretty_player_name(name: &str) -> String {
     let mut c = name.chars();
     match c.next() {
@@ -251,14 +1297,32 @@ impl serenity::client::EventHandler for Handler {
 }
 
 impl Discord {
-    pub async fn new(store: Arc<Mutex<PlayerStore>>) -> Self {
+    pub async fn new(
+        store: SharedPlayerStore,
+        stats_source: Arc<dyn StatsSource>,
+        refresh_jobs: Arc<RefreshJobs>,
+        templates: Arc<Templates>,
+    ) -> Self {
         info!("Configuring discord bot");
-        let config = store.lock().await.config.clone();
+        let config = store.read().await.config.clone();
         let intents = GatewayIntents::GUILD_VOICE_STATES | GatewayIntents::GUILDS;
 
         let framework = poise::Framework::builder()
             .options(poise::FrameworkOptions {
-                commands: vec![register(), refresh(), stat(), stats(), marius()],
+                commands: vec![
+                    register(),
+                    unregister(),
+                    unregister_player(),
+                    forgetme(),
+                    refresh(),
+                    stat(),
+                    stats(),
+                    marius(),
+                    announce(),
+                    accounts(),
+                    language(),
+                    server_language(),
+                ],
                 ..Default::default()
             })
             .setup(|ctx, _ready, framework| {
@@ -279,6 +1343,9 @@ impl Discord {
                         .await?;
                         Ok(DiscordState {
                             player_store: store,
+                            stats_source,
+                            refresh_jobs,
+                            templates,
                         })
                     })
                 } else {
@@ -288,6 +1355,9 @@ impl Discord {
                             .await?;
                         Ok(DiscordState {
                             player_store: store,
+                            stats_source,
+                            refresh_jobs,
+                            templates,
                         })
                     })
                 }
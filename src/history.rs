@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{model::player_stat::UggRank, player_store::PlayerWithStats};
+
+/// What a player looked like at a given refresh.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StatsSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub rank: Option<UggRank>,
+    pub matches_played: i32,
+    pub wins: i32,
+}
+
+impl StatsSnapshot {
+    pub fn new(player: &PlayerWithStats, taken_at: DateTime<Utc>) -> Self {
+        StatsSnapshot {
+            taken_at,
+            rank: player.rank.clone(),
+            matches_played: player.get_all_matches(),
+            wins: player.get_wins(),
+        }
+    }
+
+    /// Same rank and same games, whenever they were taken.
+    pub fn has_same_stats(&self, other: &StatsSnapshot) -> bool {
+        self.rank == other.rank
+            && self.matches_played == other.matches_played
+            && self.wins == other.wins
+    }
+}
+
+/// Progress made by a player between two snapshots.
+#[derive(Debug, Clone)]
+pub struct StatsDelta {
+    pub from: StatsSnapshot,
+    pub to: StatsSnapshot,
+    pub matches_played: i32,
+    pub wins: i32,
+}
+
+/// Games played and won over a period, summed over the accounts of a user.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GamesPlayed {
+    pub matches_played: i32,
+    pub wins: i32,
+}
+
+/// Time series of snapshots, per u.gg profile URL, oldest first.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct StatsHistory {
+    snapshots: BTreeMap<String, Vec<StatsSnapshot>>,
+}
+
+impl StatsHistory {
+    pub fn record(&mut self, rematch_url: &str, snapshot: StatsSnapshot) {
+        let snapshots = self.snapshots.entry(rematch_url.to_owned()).or_default();
+        let position = snapshots.partition_point(|s| s.taken_at <= snapshot.taken_at);
+        snapshots.insert(position, snapshot);
+    }
+
+    /// Records the snapshot only when the stats moved since the latest one, so that a player not
+    /// playing does not grow the history. Returns whether it was recorded.
+    pub fn record_change(&mut self, rematch_url: &str, snapshot: StatsSnapshot) -> bool {
+        let unchanged = self
+            .get_latest(rematch_url)
+            .is_some_and(|latest| latest.has_same_stats(&snapshot));
+        if unchanged {
+            return false;
+        }
+
+        self.record(rematch_url, snapshot);
+        true
+    }
+
+    pub fn remove(&mut self, rematch_url: &str) {
+        self.snapshots.remove(rematch_url);
+    }
+
+    /// Drops snapshots older than the retention, `None` keeps everything. The latest snapshot of
+    /// a profile is kept however old, it still tells the current stats of an idle player.
+    pub fn prune(&mut self, retention: Option<TimeDelta>, now: DateTime<Utc>) {
+        let Some(retention) = retention else {
+            return;
+        };
+
+        let oldest_kept = now - retention;
+        for snapshots in self.snapshots.values_mut() {
+            let expired_count = snapshots
+                .partition_point(|s| s.taken_at < oldest_kept)
+                .min(snapshots.len().saturating_sub(1));
+            snapshots.drain(..expired_count);
+        }
+        self.snapshots.retain(|_, snapshots| !snapshots.is_empty());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<StatsSnapshot>)> {
+        self.snapshots.iter()
+    }
+
+    pub fn get_snapshots(&self, rematch_url: &str) -> &[StatsSnapshot] {
+        self.snapshots
+            .get(rematch_url)
+            .map(|s| s.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn get_latest(&self, rematch_url: &str) -> Option<&StatsSnapshot> {
+        self.get_snapshots(rematch_url).last()
+    }
+
+    /// Snapshots taken within `[from, to]`.
+    pub fn snapshots_between(
+        &self,
+        rematch_url: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> &[StatsSnapshot] {
+        let snapshots = self.get_snapshots(rematch_url);
+        let start = snapshots.partition_point(|s| s.taken_at < from);
+        let end = snapshots.partition_point(|s| s.taken_at <= to);
+
+        if start >= end {
+            return &[];
+        }
+        &snapshots[start..end]
+    }
+
+    /// How many games were played and won within `[from, to]`, e.g. over the last week.
+    /// Snapshots are only taken when the stats move, so the stats at a date are those of the
+    /// latest snapshot at or before it. A window opening before the first snapshot counts from it.
+    pub fn stats_between(
+        &self,
+        rematch_url: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Option<StatsDelta> {
+        if from > to {
+            return None;
+        }
+
+        let snapshots = self.get_snapshots(rematch_url);
+        let latest_at = |date: DateTime<Utc>| {
+            let count = snapshots.partition_point(|s| s.taken_at <= date);
+            count.checked_sub(1).map(|i| &snapshots[i])
+        };
+        let last = latest_at(to)?;
+        let first = latest_at(from).unwrap_or(&snapshots[0]);
+
+        Some(StatsDelta {
+            from: first.clone(),
+            to: last.clone(),
+            matches_played: last.matches_played - first.matches_played,
+            wins: last.wins - first.wins,
+        })
+    }
+
+    /// First snapshot matching the predicate, e.g. when a league was reached.
+    pub fn find_first(
+        &self,
+        rematch_url: &str,
+        predicate: impl Fn(&StatsSnapshot) -> bool,
+    ) -> Option<&StatsSnapshot> {
+        self.get_snapshots(rematch_url)
+            .iter()
+            .find(|s| predicate(s))
+    }
+}
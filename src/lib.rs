@@ -1,6 +1,13 @@
+pub mod circuit_breaker;
 pub mod config;
 pub mod discord;
+pub mod history;
+pub mod locale;
 pub mod model;
 pub mod player_store;
+pub mod refresh_job;
 pub mod scraper;
 pub mod server;
+pub mod stats_source;
+pub mod storage;
+pub mod templates;
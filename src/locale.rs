@@ -0,0 +1,518 @@
+use std::fmt;
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+/// Languages the bot answers in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    Fr,
+    En,
+}
+
+/// A value given to a message, replacing the `{name}` placeholder.
+pub type MessageArg<'a> = (&'a str, &'a dyn fmt::Display);
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::Fr, Locale::En];
+
+    /// Prefix of the matching discord locales, e.g. `en` for `en-US` and `en-GB`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::Fr => "fr",
+            Locale::En => "en",
+        }
+    }
+
+    /// Every message of the language, keyed by what it is about.
+    pub fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::Fr => FR,
+            Locale::En => EN,
+        }
+    }
+
+    /// A message missing from the language falls back on the default one.
+    pub fn message(&self, key: &str, args: &[MessageArg]) -> String {
+        let template = find(self.catalog(), key).or_else(|| find(Locale::default().catalog(), key));
+        match template {
+            Some(template) => fill(template, args),
+            None => {
+                error!("No message {} in the catalog", key);
+                String::from(key)
+            }
+        }
+    }
+}
+
+fn find(catalog: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    catalog
+        .iter()
+        .find(|(message_key, _)| *message_key == key)
+        .map(|(_, template)| *template)
+}
+
+/// Replaces each `{name}` with its argument, placeholders without one are left as they are.
+pub fn fill(template: &str, args: &[MessageArg]) -> String {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let arg = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            args.iter()
+                .find(|(arg_name, _)| *arg_name == name)
+                .map(|(_, value)| (end, value))
+        });
+        match arg {
+            Some((end, value)) => {
+                message.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                message.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    message.push_str(rest);
+
+    message
+}
+
+const FR: &[(&str, &str)] = &[
+    (
+        "register.missing_profile",
+        "Fais un effort {user}, donne l'URL de ta page u.gg ou bien ta plateforme et ton identifiant",
+    ),
+    (
+        "register.name_only",
+        "Désolé {user}, u.gg ne retrouve pas un profil à partir de son nom, donne aussi ta plateforme et ton identifiant, ou bien l'URL de ta page u.gg",
+    ),
+    (
+        "register.conflicting_profile",
+        "Fais un effort {user}, donne soit l'URL de ta page u.gg, soit ta plateforme et ton identifiant, mais pas les deux",
+    ),
+    (
+        "register.unknown_profile",
+        "Fais un effort {user}, u.gg ne connaît pas ce profil ({error})",
+    ),
+    (
+        "register.done",
+        "Très bien {user}, on a enregistré la page u.gg de {name} !",
+    ),
+    (
+        "register.done_unchecked",
+        "Très bien {user}, on a enregistré la page u.gg de {name} ! u.gg ne répond pas, on verra au prochain refresh si elle existe bien.",
+    ),
+    (
+        "register.done_without_name",
+        "Très bien {user}, on a enregistré ta page u.gg !",
+    ),
+    ("register.wrong_url", "Fais un effort {user}, {error}"),
+    (
+        "register.already_registered",
+        "Désolé {user}, ce profil u.gg est déjà enregistré par {owner}",
+    ),
+    (
+        "profile_error.url",
+        "ton URL doit ressembler à {example}, sinon donne ta plateforme et ton identifiant",
+    ),
+    (
+        "profile_error.unknown_platform",
+        "{platform} n'est pas une plateforme u.gg, c'est steam, xbox, psn ou epic",
+    ),
+    (
+        "profile_error.missing_platform_id",
+        "il manque l'identifiant du compte à la fin de ton URL",
+    ),
+    (
+        "unregister.confirm_account",
+        "Tu veux vraiment supprimer ton compte {account} ?",
+    ),
+    (
+        "unregister.confirm_all",
+        "Tu veux vraiment supprimer tous tes comptes de ce serveur ?",
+    ),
+    (
+        "unregister.nothing",
+        "Aucun compte à supprimer, tu n'étais pas enregistré",
+    ),
+    (
+        "unregister.done",
+        "C'est fait {user}, tu n'es plus enregistré",
+    ),
+    (
+        "unregister_player.confirm_account",
+        "Tu veux vraiment supprimer le compte {account} de {user} ?",
+    ),
+    (
+        "unregister_player.confirm_all",
+        "Tu veux vraiment supprimer tous les comptes de {user} ?",
+    ),
+    (
+        "unregister_player.nothing",
+        "{user} n'avait aucun compte à supprimer",
+    ),
+    (
+        "unregister_player.done",
+        "C'est fait, {user} n'est plus enregistré",
+    ),
+    (
+        "forgetme.confirm",
+        "Tu veux vraiment qu'on oublie tous tes comptes, tes stats et ton historique, sur tous les serveurs ?",
+    ),
+    ("forgetme.nothing", "On ne savait déjà rien de toi"),
+    ("forgetme.done", "Adieu {user}, on a tout oublié"),
+    ("confirm.delete", "Supprimer"),
+    ("confirm.cancel", "Annuler"),
+    ("confirm.cancelled", "Ok, on ne touche à rien"),
+    ("confirm.timeout", "Trop tard, on ne touche à rien"),
+    (
+        "refresh.circuit_open",
+        "u.gg a l'air en panne, on le laisse souffler encore {minutes} min avant de réessayer.",
+    ),
+    (
+        "refresh.started",
+        "On démarre le scraping intensif, ça peut prendre quelques secondes. SVP u.gg ne portez pas plainte !",
+    ),
+    (
+        "refresh.joined",
+        "Un refresh est déjà en cours, je te tiens au courant.",
+    ),
+    (
+        "refresh.progress",
+        "Scraping en cours : {done}/{total} joueurs…",
+    ),
+    (
+        "refresh.stopped",
+        "Le refresh s'est arrêté en route, réessaie plus tard.",
+    ),
+    (
+        "refresh.summary",
+        "{ok} ok, {failed} en échec en {seconds}s",
+    ),
+    ("refresh.summary_failures", "{summary} : {failures}"),
+    ("scrape_error.page_init", "page u.gg inaccessible"),
+    (
+        "scrape_error.request_not_found",
+        "profil pas chargé par la page u.gg",
+    ),
+    ("scrape_error.not_found", "profil introuvable"),
+    ("scrape_error.content", "réponse u.gg illisible"),
+    ("scrape_error.timeout", "trop lent à répondre"),
+    ("scrape_error.parse", "format u.gg inconnu"),
+    ("scrape_error.circuit_open", "u.gg en pause"),
+    (
+        "rank_change.first",
+        "Bienvenue dans le classement {player}, tu démarres en {rank} !",
+    ),
+    (
+        "rank_change.promotion",
+        "Félicitations {player}, passage en {rank} !",
+    ),
+    (
+        "rank_change.demotion",
+        "Aïe {player}, retour en {rank}... On y croit pour la remontée !",
+    ),
+    (
+        "announce.set",
+        "Les changements de rang seront annoncés dans {channel}",
+    ),
+    (
+        "announce.unset",
+        "Plus aucun changement de rang ne sera annoncé",
+    ),
+    (
+        "accounts.aggregate",
+        "Les comptes d'un même joueur seront cumulés dans /classement",
+    ),
+    (
+        "accounts.list",
+        "Chaque compte aura sa propre ligne dans /classement",
+    ),
+    (
+        "language.user",
+        "Je te répondrai en français, même sur un serveur réglé dans une autre langue",
+    ),
+    (
+        "language.user_reset",
+        "Je te répondrai dans la langue de chaque serveur",
+    ),
+    ("language.guild", "Je répondrai en français sur ce serveur"),
+    (
+        "stat.no_account",
+        "{user} n'a pas de compte {account}, pense à utiliser la commande /inscription",
+    ),
+    (
+        "stat.not_registered",
+        "{user} n'est pas enregistré, pense à utiliser la commande /inscription",
+    ),
+    (
+        "stat.never_played",
+        "**{user}** n'a pas encore joué en mode {mode}",
+    ),
+    ("stat.title", "{user} aussi connu sous le nom {name}"),
+    ("stat.rank", "Rang"),
+    ("stat.record", "Victoires / défaites"),
+    ("stat.win_rate", "Win rate"),
+    ("stat.level", "Niveau"),
+    ("stat.play_time", "Temps de jeu"),
+    ("stat.hours", "{hours}h environ"),
+    ("stat.last_refresh", "Dernier refresh"),
+    ("stat.never_refreshed", "jamais"),
+    (
+        "stat.counters",
+        "{goals} buts, {assists} passes décisives, {saves} arrêts, {mvps} MVP",
+    ),
+    ("stat.record_value", "{wins}V {losses}D"),
+    ("stat.summary", "{record}, {win_rate}% de victoires"),
+    ("stat.stale", "données du {date}"),
+    ("stat.stale_date_format", "%d/%m"),
+    ("stat.stale_unknown", "données anciennes"),
+    ("rank.unranked", "non classé"),
+    ("rank.ranked", "{league} {division}"),
+    ("rank.league.bronze", "bronze"),
+    ("rank.league.silver", "argent"),
+    ("rank.league.gold", "or"),
+    ("rank.league.platinum", "platine"),
+    ("rank.league.diamond", "diamant"),
+    ("rank.league.master", "maître"),
+    ("rank.league.elite", "élite"),
+    ("rank.league.unknown", "ligue inconnue {league}"),
+    ("rank.division.three", "3"),
+    ("rank.division.two", "2"),
+    ("rank.division.one", "1"),
+    ("rank.division.unknown", "division inconnue {division}"),
+    (
+        "stats.empty",
+        "Personne n'est enregistré ici, pense à utiliser la commande /inscription",
+    ),
+    ("stats.title", "Classement"),
+    ("stats.page", "Page {page}/{pages}"),
+    ("stats.try_harder_title", "Try harder"),
+    (
+        "stats.try_harder",
+        "Et la palme d'or du plus gros try harder revient à {player} avec plus de {hours}h de jeu cette saison !",
+    ),
+    (
+        "marius.not_in_voice",
+        "Rejoins le serveur vocal avant d'exécuter cette commande ! 😉",
+    ),
+];
+
+const EN: &[(&str, &str)] = &[
+    (
+        "register.missing_profile",
+        "Come on {user}, give the URL of your u.gg page or your platform and account id",
+    ),
+    (
+        "register.name_only",
+        "Sorry {user}, u.gg cannot find a profile from its name, give your platform and account id as well, or the URL of your u.gg page",
+    ),
+    (
+        "register.conflicting_profile",
+        "Come on {user}, give either the URL of your u.gg page or your platform and account id, not both",
+    ),
+    (
+        "register.unknown_profile",
+        "Come on {user}, u.gg does not know this profile ({error})",
+    ),
+    (
+        "register.done",
+        "Alright {user}, the u.gg page of {name} is registered!",
+    ),
+    (
+        "register.done_unchecked",
+        "Alright {user}, the u.gg page of {name} is registered! u.gg is not answering, the next refresh will tell whether it exists.",
+    ),
+    (
+        "register.done_without_name",
+        "Alright {user}, your u.gg page is registered!",
+    ),
+    ("register.wrong_url", "Come on {user}, {error}"),
+    (
+        "register.already_registered",
+        "Sorry {user}, this u.gg profile is already registered by {owner}",
+    ),
+    (
+        "profile_error.url",
+        "your URL should look like {example}, otherwise give your platform and account id",
+    ),
+    (
+        "profile_error.unknown_platform",
+        "{platform} is not a u.gg platform, it is steam, xbox, psn or epic",
+    ),
+    (
+        "profile_error.missing_platform_id",
+        "the account id is missing at the end of your URL",
+    ),
+    (
+        "unregister.confirm_account",
+        "Do you really want to remove your account {account}?",
+    ),
+    (
+        "unregister.confirm_all",
+        "Do you really want to remove all your accounts from this server?",
+    ),
+    (
+        "unregister.nothing",
+        "No account to remove, you were not registered",
+    ),
+    (
+        "unregister.done",
+        "Done {user}, you are no longer registered",
+    ),
+    (
+        "unregister_player.confirm_account",
+        "Do you really want to remove the account {account} of {user}?",
+    ),
+    (
+        "unregister_player.confirm_all",
+        "Do you really want to remove all the accounts of {user}?",
+    ),
+    (
+        "unregister_player.nothing",
+        "{user} had no account to remove",
+    ),
+    (
+        "unregister_player.done",
+        "Done, {user} is no longer registered",
+    ),
+    (
+        "forgetme.confirm",
+        "Do you really want us to forget all your accounts, stats and history, on every server?",
+    ),
+    ("forgetme.nothing", "We already knew nothing about you"),
+    ("forgetme.done", "Farewell {user}, everything is forgotten"),
+    ("confirm.delete", "Delete"),
+    ("confirm.cancel", "Cancel"),
+    ("confirm.cancelled", "Ok, nothing was touched"),
+    ("confirm.timeout", "Too late, nothing was touched"),
+    (
+        "refresh.circuit_open",
+        "u.gg looks down, we let it breathe for {minutes} more min before trying again.",
+    ),
+    (
+        "refresh.started",
+        "Starting the intensive scraping, it can take a few seconds. Please u.gg do not sue us!",
+    ),
+    (
+        "refresh.joined",
+        "A refresh is already running, I will keep you posted.",
+    ),
+    ("refresh.progress", "Scraping: {done}/{total} players…"),
+    (
+        "refresh.stopped",
+        "The refresh stopped halfway, try again later.",
+    ),
+    ("refresh.summary", "{ok} ok, {failed} failed in {seconds}s"),
+    ("refresh.summary_failures", "{summary}: {failures}"),
+    ("scrape_error.page_init", "u.gg page unreachable"),
+    (
+        "scrape_error.request_not_found",
+        "profile not loaded by the u.gg page",
+    ),
+    ("scrape_error.not_found", "profile not found"),
+    ("scrape_error.content", "unreadable u.gg answer"),
+    ("scrape_error.timeout", "too slow to answer"),
+    ("scrape_error.parse", "unknown u.gg format"),
+    ("scrape_error.circuit_open", "u.gg on a break"),
+    (
+        "rank_change.first",
+        "Welcome to the leaderboard {player}, you start in {rank}!",
+    ),
+    (
+        "rank_change.promotion",
+        "Congratulations {player}, up to {rank}!",
+    ),
+    (
+        "rank_change.demotion",
+        "Ouch {player}, back to {rank}... We believe in the comeback!",
+    ),
+    (
+        "announce.set",
+        "Rank changes will be announced in {channel}",
+    ),
+    (
+        "announce.unset",
+        "Rank changes will not be announced anymore",
+    ),
+    (
+        "accounts.aggregate",
+        "The accounts of a user will be added up in /stats",
+    ),
+    (
+        "accounts.list",
+        "Each account will get its own line in /stats",
+    ),
+    (
+        "language.user",
+        "I will answer you in English, even on a server set to another language",
+    ),
+    (
+        "language.user_reset",
+        "I will answer you in the language of each server",
+    ),
+    ("language.guild", "I will answer in English on this server"),
+    (
+        "stat.no_account",
+        "{user} has no account {account}, remember to use the /register command",
+    ),
+    (
+        "stat.not_registered",
+        "{user} is not registered, remember to use the /register command",
+    ),
+    ("stat.never_played", "**{user}** has not played {mode} yet"),
+    ("stat.title", "{user} also known as {name}"),
+    ("stat.rank", "Rank"),
+    ("stat.record", "Wins / losses"),
+    ("stat.win_rate", "Win rate"),
+    ("stat.level", "Level"),
+    ("stat.play_time", "Play time"),
+    ("stat.hours", "about {hours}h"),
+    ("stat.last_refresh", "Last refresh"),
+    ("stat.never_refreshed", "never"),
+    (
+        "stat.counters",
+        "{goals} goals, {assists} assists, {saves} saves, {mvps} MVP",
+    ),
+    ("stat.record_value", "{wins}W {losses}L"),
+    ("stat.summary", "{record}, {win_rate}% Win Rate"),
+    ("stat.stale", "data from {date}"),
+    ("stat.stale_date_format", "%m/%d"),
+    ("stat.stale_unknown", "old data"),
+    ("rank.unranked", "unranked"),
+    ("rank.ranked", "{league} {division}"),
+    ("rank.league.bronze", "Bronze"),
+    ("rank.league.silver", "Silver"),
+    ("rank.league.gold", "Gold"),
+    ("rank.league.platinum", "Platinum"),
+    ("rank.league.diamond", "Diamond"),
+    ("rank.league.master", "Master"),
+    ("rank.league.elite", "Elite"),
+    ("rank.league.unknown", "unknown league {league}"),
+    ("rank.division.three", "3"),
+    ("rank.division.two", "2"),
+    ("rank.division.one", "1"),
+    ("rank.division.unknown", "unknown division {division}"),
+    (
+        "stats.empty",
+        "Nobody is registered here, remember to use the /register command",
+    ),
+    ("stats.title", "Leaderboard"),
+    ("stats.page", "Page {page}/{pages}"),
+    ("stats.try_harder_title", "Try harder"),
+    (
+        "stats.try_harder",
+        "And the golden palm of the biggest try harder goes to {player} with more than {hours}h played this season!",
+    ),
+    (
+        "marius.not_in_voice",
+        "Join the voice channel before running this command! 😉",
+    ),
+];
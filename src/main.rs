@@ -1,60 +1,118 @@
 use std::sync::Arc;
 
-use log::{debug, info};
+use log::{debug, error, info};
 use rebot::{
-    config::Config, discord::Discord, player_store::PlayerStore, server::start_http_server,
+    config::Config,
+    discord::{Discord, announce_rank_changes},
+    player_store::{PlayerStore, SharedPlayerStore},
+    refresh_job::RefreshJobs,
+    server::start_http_server,
+    stats_source::{StatsSource, build_stats_source},
+    templates::Templates,
+};
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::RwLock,
 };
-use tokio::sync::Mutex;
 
 use std::time::Duration;
 use tokio::time;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    if let Err(_) = dotenvy::dotenv() {
+    if dotenvy::dotenv().is_err() {
         debug!(".env file not found");
     }
     env_logger::init();
 
     let config = Config::new();
 
-    let store = PlayerStore::load_database(&config);
-    let store = Arc::new(Mutex::new(store));
+    let source = build_stats_source(&config).expect("Could not create the stats source");
+
+    let store = match PlayerStore::load_database(&config) {
+        Ok(store) => store,
+        Err(e) => {
+            error!("Could not load {} database, {:?}", config.database_path, e);
+            std::process::exit(1);
+        }
+    };
+    let store = Arc::new(RwLock::new(store));
     let cron_store = store.clone();
-    let mut discord = Discord::new(store).await;
+    let exit_store = store.clone();
+    let refresh_jobs = Arc::new(RefreshJobs::new(store.clone(), source.clone()));
+    let templates = Arc::new(Templates::new(&config.templates_path));
+    let mut discord = Discord::new(
+        store,
+        source.clone(),
+        refresh_jobs.clone(),
+        templates.clone(),
+    )
+    .await;
+    let discord_ctx = discord.get_context();
+    // Sent by docker stop
+    let mut terminate = signal(SignalKind::terminate())?;
 
     tokio::select! {
-        _ = start_http_server(&config, discord.get_context())
+        _ = start_http_server(&config, discord_ctx.clone(), source.clone())
             .launch() => {
             info!("Server stopped.");
         }
         _ = discord.start() => {
             info!("Discord bot stopped.");
         }
-        _ = cron_refresh(cron_store) => {
+        _ = cron_refresh(cron_store, source.clone(), refresh_jobs, templates, discord_ctx.clone()) => {
             info!("Refresh cron stopped.");
         }
         _ = tokio::signal::ctrl_c() => {
             info!("Ctrl+C received. Shutting down...");
         }
+        _ = terminate.recv() => {
+            info!("SIGTERM received. Shutting down...");
+        }
     }
 
+    source.shutdown().await;
+    exit_store.read().await.write_database_now();
+
     Ok(())
 }
 
-async fn cron_refresh(store: Arc<Mutex<PlayerStore>>) {
+async fn cron_refresh(
+    store: SharedPlayerStore,
+    source: Arc<dyn StatsSource>,
+    refresh_jobs: Arc<RefreshJobs>,
+    templates: Arc<Templates>,
+    discord_ctx: Arc<RwLock<Option<Arc<serenity::prelude::Context>>>>,
+) {
     let interval_second = {
-        let store = store.lock().await;
+        let store = store.read().await;
         store.config.cron_interval_minute as u64 * 60
     };
     let mut interval = time::interval(Duration::new(interval_second, 0));
     interval.tick().await;
 
     loop {
-        let execute_cron = !store.lock().await.config.skip_cron;
-        if execute_cron {
+        let execute_cron = !store.read().await.config.skip_cron;
+        let circuit_open = source
+            .circuit_status()
+            .is_some_and(|status| status.is_open());
+        if circuit_open {
+            info!("Skipping refresh, circuit breaker is open");
+        } else if execute_cron {
             debug!("Start refresh");
-            let _ = store.lock().await.refresh_all().await;
+            let job = refresh_jobs.start().await;
+            if !job.started {
+                info!("Skipping refresh, one is already running");
+            } else if let Some(report) = job.wait().await {
+                let announcements = store
+                    .read()
+                    .await
+                    .get_rank_change_announcements(&report.rank_changes);
+
+                if let Some(ctx) = discord_ctx.read().await.as_ref() {
+                    announce_rank_changes(&ctx.http, &templates, &announcements).await;
+                }
+            }
         }
 
         interval.tick().await;
@@ -1 +1,2 @@
 pub mod player_stat;
+pub mod rematch_profile;
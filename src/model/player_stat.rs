@@ -1,5 +1,9 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
 use serde::{Deserialize, Serialize};
 
+use crate::templates::Messages;
+
 #[derive(Debug, Deserialize)]
 pub struct UggPlayerStat {
     pub player: UggPlayer,
@@ -13,20 +17,246 @@ pub struct UggPlayer {
     pub level: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Ordered from the lowest rank to the highest
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct UggRank {
-    pub current_league: i32,
-    pub current_division: i32,
+    pub current_league: League,
+    pub current_division: Division,
+}
+
+impl UggRank {
+    pub fn value(&self) -> RankValue {
+        RankValue::Ranked(self.current_league, self.current_division)
+    }
+}
+
+/// Stored as the u.gg number, a number unknown to us is kept as is to survive API changes.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(from = "i32", into = "i32")]
+pub enum League {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Diamond,
+    Master,
+    Elite,
+    Unknown(i32),
+}
+
+impl League {
+    /// The top league, it has no divisions
+    pub fn has_divisions(&self) -> bool {
+        *self != League::Elite
+    }
+
+    pub fn name(&self, messages: Messages) -> String {
+        let key = match self {
+            League::Bronze => "rank.league.bronze",
+            League::Silver => "rank.league.silver",
+            League::Gold => "rank.league.gold",
+            League::Platinum => "rank.league.platinum",
+            League::Diamond => "rank.league.diamond",
+            League::Master => "rank.league.master",
+            League::Elite => "rank.league.elite",
+            League::Unknown(league) => {
+                return messages.get("rank.league.unknown", &[("league", league)]);
+            }
+        };
+
+        messages.get(key, &[])
+    }
+}
+
+impl From<i32> for League {
+    fn from(league: i32) -> Self {
+        match league {
+            0 => League::Bronze,
+            1 => League::Silver,
+            2 => League::Gold,
+            3 => League::Platinum,
+            4 => League::Diamond,
+            5 => League::Master,
+            6 => League::Elite,
+            league => League::Unknown(league),
+        }
+    }
+}
+
+impl From<League> for i32 {
+    fn from(league: League) -> Self {
+        match league {
+            League::Bronze => 0,
+            League::Silver => 1,
+            League::Gold => 2,
+            League::Platinum => 3,
+            League::Diamond => 4,
+            League::Master => 5,
+            League::Elite => 6,
+            League::Unknown(league) => league,
+        }
+    }
+}
+
+/// Ordered as u.gg numbers them, an unknown league sits where its number puts it.
+impl Ord for League {
+    fn cmp(&self, other: &Self) -> Ordering {
+        i32::from(*self).cmp(&i32::from(*other))
+    }
+}
+
+impl PartialOrd for League {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// u.gg numbers divisions from the lowest, 0 being division 3.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(from = "i32", into = "i32")]
+pub enum Division {
+    Three,
+    Two,
+    One,
+    Unknown(i32),
+}
+
+impl Division {
+    pub fn name(&self, messages: Messages) -> String {
+        let key = match self {
+            Division::Three => "rank.division.three",
+            Division::Two => "rank.division.two",
+            Division::One => "rank.division.one",
+            Division::Unknown(division) => {
+                return messages.get("rank.division.unknown", &[("division", division)]);
+            }
+        };
+
+        messages.get(key, &[])
+    }
+}
+
+impl From<i32> for Division {
+    fn from(division: i32) -> Self {
+        match division {
+            0 => Division::Three,
+            1 => Division::Two,
+            2 => Division::One,
+            division => Division::Unknown(division),
+        }
+    }
+}
+
+impl From<Division> for i32 {
+    fn from(division: Division) -> Self {
+        match division {
+            Division::Three => 0,
+            Division::Two => 1,
+            Division::One => 2,
+            Division::Unknown(division) => division,
+        }
+    }
+}
+
+impl Ord for Division {
+    fn cmp(&self, other: &Self) -> Ordering {
+        i32::from(*self).cmp(&i32::from(*other))
+    }
+}
+
+impl PartialOrd for Division {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A rank that can be compared with any other one, unranked players coming last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RankValue {
+    Unranked,
+    Ranked(League, Division),
+}
+
+impl RankValue {
+    pub fn name(&self, messages: Messages) -> String {
+        match self {
+            RankValue::Unranked => messages.get("rank.unranked", &[]),
+            RankValue::Ranked(league, _) if !league.has_divisions() => league.name(messages),
+            RankValue::Ranked(league, division) => messages.get(
+                "rank.ranked",
+                &[
+                    ("league", &league.name(messages)),
+                    ("division", &division.name(messages)),
+                ],
+            ),
+        }
+    }
+}
+
+impl From<Option<&UggRank>> for RankValue {
+    fn from(rank: Option<&UggRank>) -> Self {
+        rank.map(UggRank::value).unwrap_or(RankValue::Unranked)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct UggLifetimeStats {
     #[serde(rename = "All")]
-    pub all: UggAllLifetimeStats,
+    pub all: UggModeStats,
+    /// Every other game mode bucket, keyed as in the u.gg payload
+    #[serde(flatten)]
+    pub modes: BTreeMap<String, UggModeStats>,
+}
+
+impl UggLifetimeStats {
+    pub fn get_mode(&self, mode: &str) -> Option<&UggModeStats> {
+        if mode == "All" {
+            return Some(&self.all);
+        }
+        self.modes.get(mode)
+    }
+
+    /// Adds up the stats of another account, mode by mode.
+    pub fn add(&mut self, other: &UggLifetimeStats) {
+        self.all.add(&other.all);
+        for (mode, stats) in &other.modes {
+            match self.modes.get_mut(mode) {
+                Some(mode_stats) => mode_stats.add(stats),
+                None => {
+                    self.modes.insert(mode.clone(), stats.clone());
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct UggAllLifetimeStats {
+pub struct UggModeStats {
     pub matches_played: i32,
     pub wins: i32,
+
+    #[serde(default)]
+    pub goals: i32,
+    #[serde(default)]
+    pub assists: i32,
+    #[serde(default)]
+    pub saves: i32,
+    #[serde(default)]
+    pub mvps: i32,
+
+    /// Counters we do not display yet, kept so they end up in the database
+    #[serde(flatten)]
+    pub other_counters: BTreeMap<String, serde_json::Value>,
+}
+
+impl UggModeStats {
+    /// Counters we do not display are left as they are.
+    pub fn add(&mut self, other: &UggModeStats) {
+        self.matches_played += other.matches_played;
+        self.wins += other.wins;
+        self.goals += other.goals;
+        self.assists += other.assists;
+        self.saves += other.saves;
+        self.mvps += other.mvps;
+    }
 }
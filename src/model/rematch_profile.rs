@@ -0,0 +1,145 @@
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use url::Url;
+
+const PROFILE_HOST: &str = "u.gg";
+const PROFILE_PATH: [&str; 2] = ["rematch", "profile"];
+
+/// What u.gg encodes in the display name of its profile URLs, an apostrophe is left as is.
+const DISPLAY_NAME_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Steam,
+    Xbox,
+    Psn,
+    Epic,
+}
+
+impl Platform {
+    pub fn parse(platform: &str) -> Option<Platform> {
+        match platform.to_lowercase().as_str() {
+            "steam" => Some(Platform::Steam),
+            "xbox" => Some(Platform::Xbox),
+            "psn" => Some(Platform::Psn),
+            "epic" => Some(Platform::Epic),
+            _ => None,
+        }
+    }
+
+    /// Name of the platform in u.gg URLs
+    pub fn ugg_key(&self) -> &'static str {
+        match self {
+            Platform::Steam => "steam",
+            Platform::Xbox => "xbox",
+            Platform::Psn => "psn",
+            Platform::Epic => "epic",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProfileError {
+    /// Not even a URL
+    Url(String),
+    /// A URL, but not the one of a u.gg Rematch profile
+    NotAProfile,
+    UnknownPlatform(String),
+    MissingPlatformId,
+}
+
+/// A u.gg Rematch profile, as found in `https://u.gg/rematch/profile/{platform}/{name}/{id}`.
+#[derive(Debug, Clone)]
+pub struct RematchProfile {
+    pub platform: Platform,
+    /// Only there for humans, u.gg finds the profile from the platform and its id
+    pub display_name: String,
+    pub platform_id: String,
+}
+
+impl RematchProfile {
+    /// Accepts any spelling of a profile URL: missing scheme, `www.`, query string, trailing
+    /// slash or another percent-encoding.
+    pub fn parse(rematch_url: &str) -> Result<RematchProfile, ProfileError> {
+        let rematch_url = rematch_url.trim();
+        let url = match Url::parse(rematch_url) {
+            Ok(url) => url,
+            Err(url::ParseError::RelativeUrlWithoutBase) => {
+                Url::parse(&format!("https://{}", rematch_url))
+                    .map_err(|e| ProfileError::Url(e.to_string()))?
+            }
+            Err(e) => return Err(ProfileError::Url(e.to_string())),
+        };
+
+        let host = url.host_str().unwrap_or_default();
+        if !matches!(url.scheme(), "http" | "https")
+            || host.trim_start_matches("www.") != PROFILE_HOST
+        {
+            return Err(ProfileError::NotAProfile);
+        }
+
+        let segments: Vec<&str> = url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        let (prefix, segments) = segments.split_at(segments.len().min(PROFILE_PATH.len()));
+        if prefix != PROFILE_PATH {
+            return Err(ProfileError::NotAProfile);
+        }
+
+        match segments {
+            [platform, display_name, platform_id] => {
+                let platform = Platform::parse(platform)
+                    .ok_or_else(|| ProfileError::UnknownPlatform(decode(platform)))?;
+
+                Ok(RematchProfile {
+                    platform,
+                    display_name: decode(display_name),
+                    platform_id: decode(platform_id),
+                })
+            }
+            [_, _] => Err(ProfileError::MissingPlatformId),
+            _ => Err(ProfileError::NotAProfile),
+        }
+    }
+
+    /// Both point to the same account, whatever the display name at the time.
+    pub fn is_same_profile(&self, other: &RematchProfile) -> bool {
+        self.platform == other.platform && self.platform_id == other.platform_id
+    }
+
+    /// Names the files kept per account, e.g. `steam-76561198355389674`.
+    pub fn file_stem(&self) -> String {
+        format!(
+            "{}-{}",
+            self.platform.ugg_key(),
+            utf8_percent_encode(&self.platform_id, DISPLAY_NAME_ENCODE_SET)
+        )
+    }
+
+    /// The one spelling of the profile URL kept in the database.
+    pub fn to_url(&self) -> String {
+        format!(
+            "https://{}/{}/{}/{}/{}",
+            PROFILE_HOST,
+            PROFILE_PATH.join("/"),
+            self.platform.ugg_key(),
+            utf8_percent_encode(&self.display_name, DISPLAY_NAME_ENCODE_SET),
+            utf8_percent_encode(&self.platform_id, DISPLAY_NAME_ENCODE_SET)
+        )
+    }
+}
+
+fn decode(segment: &str) -> String {
+    percent_decode_str(segment).decode_utf8_lossy().into_owned()
+}
@@ -1,32 +1,101 @@
 use std::{
-    fs::{self, File},
-    io::Write,
+    cmp::Ordering,
+    sync::{
+        Arc, Mutex,
+        atomic::{self, AtomicU64},
+    },
+    time::{Duration, Instant},
 };
 
-use log::{debug, error};
+use chrono::{DateTime, Local, Utc};
+use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
 
 use crate::{
     config::Config,
-    model::player_stat::{UggLifetimeStats, UggRank},
-    scraper::Scraper,
+    history::{GamesPlayed, StatsHistory, StatsSnapshot},
+    locale::Locale,
+    model::{
+        player_stat::{RankValue, UggLifetimeStats, UggModeStats, UggPlayerStat, UggRank},
+        rematch_profile::{ProfileError, RematchProfile},
+    },
+    scraper::ScrapeError,
+    stats_source::PlayerFetch,
+    storage::{Storage, StorageError, build_storage},
+    templates::Messages,
 };
 
-#[derive(Debug, Deserialize, Serialize)]
+/// The store shared by the discord commands, the cron and the refresh jobs, reads do not wait
+/// for each other and writes only hold it to apply their change.
+pub type SharedPlayerStore = Arc<RwLock<PlayerStore>>;
+
+/// A u.gg profile of a discord user registered in a guild, the same profile can be registered
+/// in several guilds and a user can register several profiles.
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RegisteredPlayer {
+    pub guild_id: u64,
     pub discord_id: u64,
     pub rematch_url: String,
+    /// Tells the accounts of a user apart, e.g. "xbox" or "smurf"
+    #[serde(default)]
+    pub label: Option<String>,
+    /// The account shown when no other one is asked for
+    #[serde(default)]
+    pub main: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PlayerWithStats {
     pub discord_id: u64,
+    /// The u.gg profile these stats come from, shared by every guild it is registered in
+    pub rematch_url: String,
     pub display_name: String,
+    #[serde(default)]
+    pub level: i32,
     pub rank: Option<UggRank>,
     pub stats: UggLifetimeStats,
+
+    #[serde(default)]
+    pub last_refreshed_at: Option<DateTime<Utc>>,
+    /// The last refresh failed for this player, stats come from an older one
+    #[serde(default)]
+    pub stale: bool,
 }
 
 impl PlayerWithStats {
+    pub fn new(player: &RegisteredPlayer, player_stat: UggPlayerStat) -> Self {
+        PlayerWithStats {
+            discord_id: player.discord_id,
+            rematch_url: player.rematch_url.clone(),
+            display_name: player_stat.player.display_name,
+            level: player_stat.player.level,
+            rank: player_stat.rank,
+            stats: player_stat.lifetime_stats,
+            last_refreshed_at: Some(Utc::now()),
+            stale: false,
+        }
+    }
+
+    /// Empty when the stats are fresh, ` (données du 12/10)` or ` (data from 10/12)` otherwise.
+    pub fn pretty_freshness(&self, messages: Messages) -> String {
+        if !self.stale {
+            return String::new();
+        }
+
+        let freshness = match self.last_refreshed_at {
+            Some(date) => {
+                let date_format = messages.get("stat.stale_date_format", &[]);
+                messages.get(
+                    "stat.stale",
+                    &[("date", &date.with_timezone(&Local).format(&date_format))],
+                )
+            }
+            None => messages.get("stat.stale_unknown", &[]),
+        };
+        format!(" ({})", freshness)
+    }
+
     pub fn get_all_matches(&self) -> i32 {
         self.stats.all.matches_played
     }
@@ -40,107 +109,298 @@ impl PlayerWithStats {
     }
 
     pub fn get_win_rate(&self) -> String {
-        let all_matches = self.get_all_matches() as f32;
+        compute_win_rate(&self.stats.all)
+    }
 
-        if all_matches == 0.0 {
-            return format!("-");
+    /// Between 0 and 1, `None` before the first game.
+    pub fn get_win_ratio(&self) -> Option<f32> {
+        match self.get_all_matches() {
+            0 => None,
+            matches => Some(self.get_wins() as f32 / matches as f32),
         }
-        let ratio = self.get_wins() as f32 / all_matches;
-        let percent = ratio * 100.;
+    }
 
-        format!("{:.1}", percent)
+    pub fn get_pretty_stats(&self, messages: Messages) -> String {
+        compute_pretty_stats(&self.stats.all, messages)
     }
 
-    pub fn get_pretty_stats(&self) -> String {
-        format!(
-            "{}W {}L, {}% Win Rate",
-            self.get_wins(),
-            self.get_loses(),
-            self.get_win_rate()
-        )
+    /// W/L of a single game mode, `None` when the player never played it.
+    pub fn get_pretty_mode_stats(&self, mode: &str, messages: Messages) -> Option<String> {
+        self.stats
+            .get_mode(mode)
+            .map(|stats| compute_pretty_stats(stats, messages))
     }
 
-    pub fn pretty_rank(&self) -> String {
-        if let None = self.rank {
-            return format!("non classé");
-        }
+    /// `101W 79L` in a single game mode.
+    pub fn get_pretty_mode_record(&self, mode: &str, messages: Messages) -> Option<String> {
+        self.stats
+            .get_mode(mode)
+            .map(|stats| compute_pretty_record(stats, messages))
+    }
 
-        let rank = self.rank.clone().unwrap();
+    pub fn get_mode_win_rate(&self, mode: &str) -> Option<String> {
+        self.stats.get_mode(mode).map(compute_win_rate)
+    }
 
-        let league = rank.current_league;
-        let pretty_league = match league {
-            0 => String::from("bronze"),
-            1 => String::from("argent"),
-            2 => String::from("or"),
-            3 => String::from("platine"),
-            4 => String::from("diamant"),
-            5 => String::from("maître"),
-            6 => String::from("élite"),
-            _ => String::from("inconnu"),
-        };
+    pub fn get_pretty_counters(&self, mode: &str, messages: Messages) -> Option<String> {
+        self.stats.get_mode(mode).map(|stats| {
+            messages.get(
+                "stat.counters",
+                &[
+                    ("goals", &stats.goals),
+                    ("assists", &stats.assists),
+                    ("saves", &stats.saves),
+                    ("mvps", &stats.mvps),
+                ],
+            )
+        })
+    }
 
-        if league == 6 {
-            return format!("{}", pretty_league);
-        }
+    pub fn rank_value(&self) -> RankValue {
+        RankValue::from(self.rank.as_ref())
+    }
 
-        let division = 3 - rank.current_division;
-        format!("{} {}", pretty_league, division)
+    pub fn pretty_rank(&self, messages: Messages) -> String {
+        self.rank_value().name(messages)
     }
 
     pub fn estimate_hours_played(&self) -> i32 {
         let minutes = self.get_all_matches() * 6;
         minutes / 60
     }
+
+    /// Adds another account of the same user, keeping its best rank and level.
+    fn merge(&mut self, other: &PlayerWithStats) {
+        self.stats.add(&other.stats);
+        self.level = self.level.max(other.level);
+        if other.rank_value() > self.rank_value() {
+            self.rank = other.rank.clone();
+        }
+        self.stale = self.stale || other.stale;
+    }
+}
+
+fn compare_players(
+    p1: &PlayerWithStats,
+    p2: &PlayerWithStats,
+    sort: LeaderboardSort,
+    min_matches: i32,
+) -> Ordering {
+    // No game played is worse than losing them all
+    let by_win_rate = || {
+        let win_ratio = |p: &PlayerWithStats| p.get_win_ratio().unwrap_or(-1.);
+        win_ratio(p1).total_cmp(&win_ratio(p2))
+    };
+
+    match sort {
+        LeaderboardSort::Rank => p1.rank_value().cmp(&p2.rank_value()).then_with(by_win_rate),
+        LeaderboardSort::WinRate => {
+            let played_enough = |p: &PlayerWithStats| p.get_all_matches() >= min_matches;
+            played_enough(p1)
+                .cmp(&played_enough(p2))
+                .then_with(by_win_rate)
+        }
+        LeaderboardSort::Matches => p1.get_all_matches().cmp(&p2.get_all_matches()),
+        LeaderboardSort::Wins => p1.get_wins().cmp(&p2.get_wins()),
+        LeaderboardSort::Level => p1.level.cmp(&p2.level),
+    }
+}
+
+fn compute_win_rate(stats: &UggModeStats) -> String {
+    let all_matches = stats.matches_played as f32;
+
+    if all_matches == 0.0 {
+        return String::from("-");
+    }
+    let ratio = stats.wins as f32 / all_matches;
+    let percent = ratio * 100.;
+
+    format!("{:.1}", percent)
+}
+
+fn compute_pretty_record(stats: &UggModeStats, messages: Messages) -> String {
+    messages.get(
+        "stat.record_value",
+        &[
+            ("wins", &stats.wins),
+            ("losses", &(stats.matches_played - stats.wins)),
+        ],
+    )
+}
+
+fn compute_pretty_stats(stats: &UggModeStats, messages: Messages) -> String {
+    messages.get(
+        "stat.summary",
+        &[
+            ("record", &compute_pretty_record(stats, messages)),
+            ("win_rate", &compute_win_rate(stats)),
+        ],
+    )
+}
+
+/// How the guild leaderboard is ordered, best first.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum LeaderboardSort {
+    /// League, division, then win rate
+    #[default]
+    Rank,
+    /// Players who did not play enough games come last
+    WinRate,
+    Matches,
+    Wins,
+    Level,
+}
+
+/// How users with several accounts show up in the guild leaderboard.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountsDisplay {
+    /// One line per user, adding up the stats of all their accounts
+    #[default]
+    Aggregate,
+    /// One line per account
+    List,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GuildSettings {
+    pub guild_id: u64,
+    /// Where rank promotions and demotions are posted
+    pub announcement_channel_id: Option<u64>,
+    #[serde(default)]
+    pub accounts_display: AccountsDisplay,
+    #[serde(default)]
+    pub locale: Locale,
+}
+
+/// Preferences of a discord user, wherever they use the bot.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UserSettings {
+    pub discord_id: u64,
+    /// Overrides the locale of the guilds
+    pub locale: Option<Locale>,
+}
+
+/// Saves run in the background and may finish out of order, only the newest snapshot is kept.
+#[derive(Debug, Default)]
+struct SaveOrder {
+    last_version: AtomicU64,
+    saved_version: Mutex<u64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct PlayerStore {
     #[serde(skip)]
     pub config: Config,
+    #[serde(skip)]
+    storage: Option<Arc<dyn Storage>>,
+    #[serde(skip)]
+    save_order: Arc<SaveOrder>,
 
     pub registered_players: Vec<RegisteredPlayer>,
     pub players: Vec<PlayerWithStats>,
+    #[serde(default)]
+    pub history: StatsHistory,
+    #[serde(default)]
+    pub guild_settings: Vec<GuildSettings>,
+    #[serde(default)]
+    pub user_settings: Vec<UserSettings>,
+}
+
+/// Registrations removed from the store.
+#[derive(Debug, Default)]
+pub struct RemovedRegistrations {
+    pub count: usize,
+    /// Profiles nobody registers anymore, their stats and history are gone as well
+    pub forgotten_profiles: Vec<String>,
 }
 
 #[derive(Debug)]
 pub enum RegisterError {
-    WrongUrl(String),
+    WrongUrl(ProfileError),
+    /// The profile was already registered by this other discord user
+    AlreadyRegistered(u64),
 }
 
+/// How the refresh of a single player went.
 #[derive(Debug)]
-pub enum RefreshError {
-    Err,
+pub struct PlayerRefresh {
+    pub discord_id: u64,
+    pub rematch_url: String,
+    /// Unknown when the player was never refreshed successfully
+    pub display_name: Option<String>,
+    pub result: Result<(), ScrapeError>,
+    pub duration: Duration,
+}
+
+/// A player moved to another rank since the previous snapshot.
+#[derive(Debug, Clone)]
+pub struct RankChange {
+    pub discord_id: u64,
+    pub rematch_url: String,
+    pub display_name: String,
+    pub from: Option<UggRank>,
+    pub to: UggRank,
+}
+
+impl RankChange {
+    pub fn is_promotion(&self) -> bool {
+        RankValue::from(self.from.as_ref()) < self.to.value()
+    }
+}
+
+/// A rank change to post in a guild announcement channel, in the language of the guild.
+#[derive(Debug, Clone)]
+pub struct RankAnnouncement {
+    pub channel_id: u64,
+    pub locale: Locale,
+    pub rank_change: RankChange,
+}
+
+#[derive(Debug, Default)]
+pub struct RefreshReport {
+    pub players: Vec<PlayerRefresh>,
+    pub rank_changes: Vec<RankChange>,
+    pub duration: Duration,
+}
+
+impl RefreshReport {
+    pub fn success_count(&self) -> usize {
+        self.players.iter().filter(|p| p.result.is_ok()).count()
+    }
+
+    pub fn failures(&self) -> Vec<(&PlayerRefresh, &ScrapeError)> {
+        self.players
+            .iter()
+            .filter_map(|p| p.result.as_ref().err().map(|e| (p, e)))
+            .collect()
+    }
 }
 
 impl PlayerStore {
-    pub fn new(config: &Config) -> Self {
+    pub fn new(config: &Config, storage: Arc<dyn Storage>) -> Self {
         PlayerStore {
             config: config.clone(),
-            registered_players: vec![],
-            players: vec![],
+            storage: Some(storage),
+            ..Default::default()
         }
     }
 
-    pub fn load_database(config: &Config) -> Self {
-        let json_data = fs::read_to_string(&config.database_path);
-        let json_data = match json_data {
-            Ok(v) => v,
-            Err(e) => {
-                error!("Could not read {} database, {}", config.database_path, e);
-                return PlayerStore::new(config);
-            }
-        };
+    /// Fails when the database cannot be opened or read, running without it would answer every
+    /// command and then lose it all.
+    pub fn load_database(config: &Config) -> Result<Self, StorageError> {
+        let storage = build_storage(config)?;
 
-        let store: PlayerStore = serde_json::from_str(&json_data).unwrap_or_else(|e| {
-            error!("Could not parse {} database, {}", config.database_path, e);
-            return PlayerStore::new(config);
-        });
+        let Some(store) = storage.load()? else {
+            info!("No {} database yet, starting empty", config.database_path);
+            return Ok(PlayerStore::new(config, storage));
+        };
 
-        PlayerStore {
+        Ok(PlayerStore {
             config: config.clone(),
-            registered_players: store.registered_players,
-            players: store.players,
-        }
+            storage: Some(storage),
+            ..store
+        })
     }
 
     pub fn print(&self) {
@@ -148,83 +408,578 @@ impl PlayerStore {
         debug!("Players: {:?}", self.players);
     }
 
+    /// Saves a snapshot off the async workers, the store is free again once this returns.
     pub fn write_database(&self) {
-        let json_data = serde_json::to_string_pretty(self);
-        let json_data = match json_data {
-            Ok(json_str) => json_str,
-            Err(e) => {
-                error!("Could not stringify store, {}", e);
-                return;
-            }
+        let Some(save) = self.prepare_save() else {
+            return;
         };
 
-        let file = File::create(&self.config.database_path);
-        let mut file = match file {
-            Ok(f) => f,
-            Err(e) => {
-                error!("Could not create {} file, {}", self.config.database_path, e);
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(save);
+            }
+            Err(_) => save(),
+        }
+    }
+
+    /// Saves right away, before exiting as background saves not started yet are dropped.
+    pub fn write_database_now(&self) {
+        if let Some(save) = self.prepare_save() {
+            save();
+        }
+    }
+
+    fn prepare_save(&self) -> Option<impl FnOnce() + Send + 'static> {
+        let storage = self.storage.clone()?;
+        let snapshot = self.snapshot();
+        let save_order = self.save_order.clone();
+        let version = save_order
+            .last_version
+            .fetch_add(1, atomic::Ordering::SeqCst)
+            + 1;
+
+        Some(move || {
+            let mut saved_version = save_order.saved_version.lock().unwrap();
+            if *saved_version >= version {
                 return;
             }
-        };
 
-        let written_bytes = file.write(json_data.as_bytes());
-        if let Err(e) = written_bytes {
-            error!("Could write to {} file, {}", self.config.database_path, e);
+            match storage.save(&snapshot) {
+                Ok(_) => *saved_version = version,
+                Err(e) => error!(
+                    "Could not write {} database, {:?}",
+                    snapshot.config.database_path, e
+                ),
+            }
+        })
+    }
+
+    /// The data of the store, without its storage.
+    fn snapshot(&self) -> PlayerStore {
+        PlayerStore {
+            config: self.config.clone(),
+            registered_players: self.registered_players.clone(),
+            players: self.players.clone(),
+            history: self.history.clone(),
+            guild_settings: self.guild_settings.clone(),
+            user_settings: self.user_settings.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Parses the profile URL and makes sure nobody else registered the profile.
+    pub fn check_profile(
+        &self,
+        discord_id: u64,
+        rematch_url: &str,
+    ) -> Result<RematchProfile, RegisterError> {
+        let profile = RematchProfile::parse(rematch_url).map_err(RegisterError::WrongUrl)?;
+
+        let other_owner = self
+            .registered_players
+            .iter()
+            .filter(|p| p.discord_id != discord_id)
+            .find(|p| {
+                RematchProfile::parse(&p.rematch_url).is_ok_and(|p| p.is_same_profile(&profile))
+            });
+        match other_owner {
+            Some(other_owner) => Err(RegisterError::AlreadyRegistered(other_owner.discord_id)),
+            None => Ok(profile),
         }
     }
 
+    /// Adds an account to the user, the first one being the main. Registering an account again
+    /// updates its label.
     pub fn register_player(
         &mut self,
+        guild_id: u64,
         discord_id: u64,
         rematch_url: &str,
+        label: Option<&str>,
+        main: bool,
     ) -> Result<(), RegisterError> {
-        if !rematch_url.starts_with("https://u.gg/rematch/profile/") {
-            return Err(RegisterError::WrongUrl(String::from(
-                "ton URL doit ressembler à https://u.gg/rematch/profile/steam/La%20m%C3%A9sange%20du%20Val%20d'Oise/76561198355389674",
-            )));
+        let profile = self.check_profile(discord_id, rematch_url)?;
+
+        let main = main || self.get_player_accounts(guild_id, discord_id).is_empty();
+        let is_same_user =
+            |p: &RegisteredPlayer| p.guild_id == guild_id && p.discord_id == discord_id;
+
+        if main {
+            for account in self
+                .registered_players
+                .iter_mut()
+                .filter(|p| is_same_user(p))
+            {
+                account.main = false;
+            }
         }
 
-        let existing_player = self
-            .registered_players
-            .iter_mut()
-            .find(|p| p.discord_id == discord_id);
-
-        match existing_player {
-            Some(player) => player.rematch_url = rematch_url.to_owned(),
+        // Kept under its first URL, the display name in it may have changed since
+        let existing_account = self.registered_players.iter_mut().find(|p| {
+            is_same_user(p)
+                && RematchProfile::parse(&p.rematch_url).is_ok_and(|p| p.is_same_profile(&profile))
+        });
+        match existing_account {
+            Some(account) => {
+                account.main |= main;
+                if label.is_some() {
+                    account.label = label.map(String::from);
+                }
+            }
             None => self.registered_players.push(RegisteredPlayer {
+                guild_id,
                 discord_id,
-                rematch_url: rematch_url.to_owned(),
+                rematch_url: profile.to_url(),
+                label: label.map(String::from),
+                main,
             }),
         }
 
         Ok(())
     }
 
-    pub async fn refresh_all(&mut self) -> Result<(), RefreshError> {
-        let scraper = Scraper::new().await;
-        let mut scraper = scraper.map_err(|_| RefreshError::Err)?;
+    /// Removes the accounts of the user in the guild, all of them when no account is given.
+    pub fn unregister_player(
+        &mut self,
+        guild_id: u64,
+        discord_id: u64,
+        account: Option<&str>,
+    ) -> RemovedRegistrations {
+        let rematch_urls: Vec<String> = self
+            .get_player_accounts(guild_id, discord_id)
+            .into_iter()
+            .filter(|p| account.is_none_or(|account| self.is_account(p, account)))
+            .map(|p| p.rematch_url.clone())
+            .collect();
+
+        self.remove_registrations(|p| {
+            p.guild_id == guild_id
+                && p.discord_id == discord_id
+                && rematch_urls.contains(&p.rematch_url)
+        })
+    }
+
+    /// Removes every account of the user, in every guild.
+    /// Also forgets the settings of the user. Database backups still hold them until they are
+    /// rotated out, after `DATABASE_BACKUP_COUNT` saves.
+    pub fn forget_player(&mut self, discord_id: u64) -> RemovedRegistrations {
+        let settings_count = self.user_settings.len();
+        self.user_settings
+            .retain(|settings| settings.discord_id != discord_id);
+
+        let removed = self.remove_registrations(|p| p.discord_id == discord_id);
+        if removed.count == 0 && settings_count != self.user_settings.len() {
+            self.write_database();
+        }
+
+        removed
+    }
+
+    /// Stats and history of a profile go along with its last registration, a profile also
+    /// registered by someone else is kept for them.
+    fn remove_registrations(
+        &mut self,
+        predicate: impl Fn(&RegisteredPlayer) -> bool,
+    ) -> RemovedRegistrations {
+        let (removed, kept): (Vec<RegisteredPlayer>, Vec<RegisteredPlayer>) =
+            std::mem::take(&mut self.registered_players)
+                .into_iter()
+                .partition(|p| predicate(p));
+        self.registered_players = kept;
+
+        if removed.is_empty() {
+            return RemovedRegistrations::default();
+        }
 
-        debug!("Scraper created");
+        // A user left with accounts still needs a main one
+        for player in &removed {
+            let has_main = self
+                .get_player_accounts(player.guild_id, player.discord_id)
+                .iter()
+                .any(|p| p.main);
+            let remaining_account = self
+                .registered_players
+                .iter_mut()
+                .find(|p| p.guild_id == player.guild_id && p.discord_id == player.discord_id);
+            if let (false, Some(account)) = (has_main, remaining_account) {
+                account.main = true;
+            }
+        }
+
+        let mut forgotten_profiles: Vec<String> = vec![];
+        for player in &removed {
+            let still_registered = self
+                .registered_players
+                .iter()
+                .any(|p| p.rematch_url == player.rematch_url);
+            if !still_registered && !forgotten_profiles.contains(&player.rematch_url) {
+                forgotten_profiles.push(player.rematch_url.clone());
+            }
+        }
+
+        for rematch_url in &forgotten_profiles {
+            self.history.remove(rematch_url);
+        }
+        self.players
+            .retain(|p| !forgotten_profiles.contains(&p.rematch_url));
 
-        self.players = scraper.get_players_stats(&self.registered_players).await;
         self.write_database();
+        info!(
+            "{} registrations removed, {} profiles forgotten",
+            removed.len(),
+            forgotten_profiles.len()
+        );
+
+        RemovedRegistrations {
+            count: removed.len(),
+            forgotten_profiles,
+        }
+    }
 
-        Ok(())
+    /// One registration per u.gg profile, so that a profile shared by guilds is scraped once.
+    pub fn get_unique_profiles(&self) -> Vec<RegisteredPlayer> {
+        let mut profiles: Vec<RegisteredPlayer> = vec![];
+        for player in &self.registered_players {
+            if !profiles.iter().any(|p| p.rematch_url == player.rematch_url) {
+                profiles.push(player.clone());
+            }
+        }
+
+        profiles
     }
 
-    pub fn get_player_stat(&self, discord_id: u64) -> Option<&PlayerWithStats> {
-        self.players
+    /// Stores the stats fetched for `profiles`, scraped while the store was free to change:
+    /// a profile unregistered in the meantime is dropped, one registered meanwhile is kept as is.
+    pub fn apply_refresh(
+        &mut self,
+        profiles: &[RegisteredPlayer],
+        fetches: Vec<PlayerFetch>,
+        start: Instant,
+    ) -> RefreshReport {
+        let refreshed_at = Utc::now();
+
+        let mut players: Vec<PlayerWithStats> = self
+            .players
+            .iter()
+            .filter(|p| !profiles.iter().any(|q| q.rematch_url == p.rematch_url))
+            .cloned()
+            .collect();
+        let mut report = RefreshReport::default();
+        for (profile, fetch) in profiles.iter().zip(fetches) {
+            let still_registered = self
+                .registered_players
+                .iter()
+                .any(|p| p.rematch_url == profile.rematch_url);
+            if !still_registered {
+                continue;
+            }
+
+            let previous = self.get_profile_stat(&profile.rematch_url);
+            let mut display_name = previous.map(|p| p.display_name.clone());
+
+            let result = match fetch.result {
+                Ok(player_stat) => {
+                    let player = PlayerWithStats::new(profile, player_stat);
+                    display_name = Some(player.display_name.clone());
+                    if let Some(rank_change) = self.detect_rank_change(&player) {
+                        report.rank_changes.push(rank_change);
+                    }
+                    self.history.record_change(
+                        &player.rematch_url,
+                        StatsSnapshot::new(&player, refreshed_at),
+                    );
+                    players.push(player);
+                    Ok(())
+                }
+                Err(e) => {
+                    // Keep showing the last known stats rather than nothing
+                    if let Some(previous) = previous {
+                        players.push(PlayerWithStats {
+                            stale: true,
+                            ..previous.clone()
+                        });
+                    }
+                    Err(e)
+                }
+            };
+
+            report.players.push(PlayerRefresh {
+                discord_id: fetch.discord_id,
+                rematch_url: profile.rematch_url.clone(),
+                display_name,
+                result,
+                duration: fetch.duration,
+            });
+        }
+
+        self.players = players;
+        self.history
+            .prune(self.config.history_retention(), refreshed_at);
+        self.write_database();
+
+        report.duration = start.elapsed();
+        info!(
+            "Refresh done in {}s, {} ok, {} failed",
+            report.duration.as_secs(),
+            report.success_count(),
+            report.failures().len()
+        );
+
+        report
+    }
+
+    /// Compares against the previous snapshot, a player losing their rank is not a change worth telling.
+    fn detect_rank_change(&self, player: &PlayerWithStats) -> Option<RankChange> {
+        let previous = self.history.get_latest(&player.rematch_url)?;
+        let to = player.rank.clone()?;
+
+        if RankValue::from(previous.rank.as_ref()) == to.value() {
+            return None;
+        }
+
+        Some(RankChange {
+            discord_id: player.discord_id,
+            rematch_url: player.rematch_url.clone(),
+            display_name: player.display_name.clone(),
+            from: previous.rank.clone(),
+            to,
+        })
+    }
+
+    pub fn get_guild_settings(&self, guild_id: u64) -> Option<&GuildSettings> {
+        self.guild_settings
+            .iter()
+            .find(|settings| settings.guild_id == guild_id)
+    }
+
+    fn get_guild_settings_mut(&mut self, guild_id: u64) -> &mut GuildSettings {
+        let position = self
+            .guild_settings
+            .iter()
+            .position(|settings| settings.guild_id == guild_id);
+
+        let position = match position {
+            Some(position) => position,
+            None => {
+                self.guild_settings.push(GuildSettings {
+                    guild_id,
+                    announcement_channel_id: None,
+                    accounts_display: AccountsDisplay::default(),
+                    locale: Locale::default(),
+                });
+                self.guild_settings.len() - 1
+            }
+        };
+
+        &mut self.guild_settings[position]
+    }
+
+    pub fn set_announcement_channel(&mut self, guild_id: u64, channel_id: Option<u64>) {
+        self.get_guild_settings_mut(guild_id)
+            .announcement_channel_id = channel_id;
+        self.write_database();
+    }
+
+    pub fn set_accounts_display(&mut self, guild_id: u64, accounts_display: AccountsDisplay) {
+        self.get_guild_settings_mut(guild_id).accounts_display = accounts_display;
+        self.write_database();
+    }
+
+    pub fn set_guild_locale(&mut self, guild_id: u64, locale: Locale) {
+        self.get_guild_settings_mut(guild_id).locale = locale;
+        self.write_database();
+    }
+
+    /// `None` goes back to the locale of each guild.
+    pub fn set_user_locale(&mut self, discord_id: u64, locale: Option<Locale>) {
+        self.user_settings
+            .retain(|settings| settings.discord_id != discord_id);
+        if locale.is_some() {
+            self.user_settings.push(UserSettings { discord_id, locale });
+        }
+        self.write_database();
+    }
+
+    /// The locale picked by the user first, then the one of the guild.
+    pub fn get_locale(&self, guild_id: Option<u64>, discord_id: u64) -> Locale {
+        let user_locale = self
+            .user_settings
+            .iter()
+            .find(|settings| settings.discord_id == discord_id)
+            .and_then(|settings| settings.locale);
+
+        user_locale
+            .or_else(|| {
+                guild_id
+                    .and_then(|guild_id| self.get_guild_settings(guild_id))
+                    .map(|settings| settings.locale)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Rank changes paired with the announcement channel of every guild the player is in.
+    pub fn get_rank_change_announcements(
+        &self,
+        rank_changes: &[RankChange],
+    ) -> Vec<RankAnnouncement> {
+        let mut announcements = vec![];
+        for rank_change in rank_changes {
+            for settings in &self.guild_settings {
+                let Some(channel_id) = settings.announcement_channel_id else {
+                    continue;
+                };
+
+                if self.is_profile_registered(settings.guild_id, &rank_change.rematch_url) {
+                    announcements.push(RankAnnouncement {
+                        channel_id,
+                        locale: settings.locale,
+                        rank_change: rank_change.clone(),
+                    });
+                }
+            }
+        }
+
+        announcements
+    }
+
+    pub fn is_profile_registered(&self, guild_id: u64, rematch_url: &str) -> bool {
+        self.registered_players
+            .iter()
+            .any(|p| p.guild_id == guild_id && p.rematch_url == rematch_url)
+    }
+
+    /// Accounts of the user in the guild, main first.
+    pub fn get_player_accounts(&self, guild_id: u64, discord_id: u64) -> Vec<&RegisteredPlayer> {
+        let mut accounts: Vec<&RegisteredPlayer> = self
+            .registered_players
             .iter()
-            .find(|player| player.discord_id == discord_id)
+            .filter(|p| p.guild_id == guild_id && p.discord_id == discord_id)
+            .collect();
+        accounts.sort_by_key(|p| !p.main);
+
+        accounts
     }
 
-    pub fn get_all_players_stat(&self) -> Vec<PlayerWithStats> {
-        self.players.clone()
+    /// Games played and won by the user within `[from, to]`, summed over their accounts in every
+    /// guild. An account registered in several guilds is counted once.
+    pub fn get_games_between(
+        &self,
+        discord_id: u64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Option<GamesPlayed> {
+        let mut rematch_urls: Vec<&str> = self
+            .registered_players
+            .iter()
+            .filter(|p| p.discord_id == discord_id)
+            .map(|p| p.rematch_url.as_str())
+            .collect();
+        rematch_urls.sort_unstable();
+        rematch_urls.dedup();
+
+        rematch_urls
+            .into_iter()
+            .filter_map(|rematch_url| self.history.stats_between(rematch_url, from, to))
+            .map(|delta| GamesPlayed {
+                matches_played: delta.matches_played,
+                wins: delta.wins,
+            })
+            .reduce(|total, games| GamesPlayed {
+                matches_played: total.matches_played + games.matches_played,
+                wins: total.wins + games.wins,
+            })
     }
 
-    pub fn find_try_harder(&self) -> Option<&PlayerWithStats> {
+    fn get_profile_stat(&self, rematch_url: &str) -> Option<&PlayerWithStats> {
         self.players
             .iter()
+            .find(|player| player.rematch_url == rematch_url)
+    }
+
+    /// Stats of the main account of the user.
+    pub fn get_player_stat(&self, guild_id: u64, discord_id: u64) -> Option<&PlayerWithStats> {
+        let main_account = *self.get_player_accounts(guild_id, discord_id).first()?;
+        self.get_profile_stat(&main_account.rematch_url)
+    }
+
+    /// Stats of the account of the user matching the label or the u.gg name.
+    pub fn get_account_stat(
+        &self,
+        guild_id: u64,
+        discord_id: u64,
+        account: &str,
+    ) -> Option<&PlayerWithStats> {
+        self.get_player_accounts(guild_id, discord_id)
+            .into_iter()
+            .find(|p| self.is_account(p, account))
+            .and_then(|p| self.get_profile_stat(&p.rematch_url))
+    }
+
+    /// Whether the account has this label or this u.gg name, ignoring case.
+    fn is_account(&self, registered_player: &RegisteredPlayer, account: &str) -> bool {
+        let account = account.trim().to_lowercase();
+        let is_label = registered_player
+            .label
+            .as_ref()
+            .is_some_and(|label| label.to_lowercase() == account);
+
+        is_label
+            || self
+                .get_profile_stat(&registered_player.rematch_url)
+                .is_some_and(|stat| stat.display_name.to_lowercase() == account)
+    }
+
+    /// Stats of the players registered in the guild, users with several accounts are shown
+    /// according to the guild settings.
+    pub fn get_all_players_stat(&self, guild_id: u64) -> Vec<PlayerWithStats> {
+        let accounts_display = self
+            .get_guild_settings(guild_id)
+            .map(|settings| settings.accounts_display)
+            .unwrap_or_default();
+
+        let mut players: Vec<PlayerWithStats> = vec![];
+        for registered_player in self
+            .registered_players
+            .iter()
+            .filter(|p| p.guild_id == guild_id)
+        {
+            let Some(stat) = self.get_profile_stat(&registered_player.rematch_url) else {
+                continue;
+            };
+
+            let same_user = players
+                .iter_mut()
+                .find(|p| p.discord_id == registered_player.discord_id);
+            match (accounts_display, same_user) {
+                (AccountsDisplay::Aggregate, Some(player)) if registered_player.main => {
+                    let mut main = stat.clone();
+                    main.discord_id = registered_player.discord_id;
+                    main.merge(player);
+                    *player = main;
+                }
+                (AccountsDisplay::Aggregate, Some(player)) => player.merge(stat),
+                _ => players.push(PlayerWithStats {
+                    discord_id: registered_player.discord_id,
+                    ..stat.clone()
+                }),
+            }
+        }
+
+        players
+    }
+
+    /// Stats of the players registered in the guild, best first.
+    pub fn get_leaderboard(&self, guild_id: u64, sort: LeaderboardSort) -> Vec<PlayerWithStats> {
+        let mut players = self.get_all_players_stat(guild_id);
+        let min_matches = self.config.leaderboard_min_matches;
+        players.sort_by(|p1, p2| compare_players(p2, p1, sort, min_matches));
+
+        players
+    }
+
+    pub fn find_try_harder(&self, guild_id: u64) -> Option<PlayerWithStats> {
+        self.get_all_players_stat(guild_id)
+            .into_iter()
             .max_by(|p1, p2| p1.get_all_matches().cmp(&p2.get_all_matches()))
     }
 }
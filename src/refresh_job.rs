@@ -0,0 +1,123 @@
+use std::{sync::Arc, time::Instant};
+
+use log::{debug, info};
+use tokio::{
+    sync::{Mutex, watch},
+    task::JoinHandle,
+};
+
+use crate::{
+    player_store::{RefreshReport, SharedPlayerStore},
+    stats_source::StatsSource,
+};
+
+#[derive(Debug, Clone)]
+pub enum RefreshState {
+    Running { done: usize, total: usize },
+    Done(Arc<RefreshReport>),
+}
+
+struct RunningRefresh {
+    task: JoinHandle<()>,
+    state: watch::Receiver<RefreshState>,
+}
+
+/// Runs refreshes in the background, one at a time, so that the store is only locked to
+/// read the profiles and to save the results, not while scraping.
+pub struct RefreshJobs {
+    store: SharedPlayerStore,
+    source: Arc<dyn StatsSource>,
+    current: Mutex<Option<RunningRefresh>>,
+}
+
+/// A refresh followed by whoever asked for it.
+pub struct RefreshJob {
+    /// False when joining a refresh already running, its starter announces the rank changes
+    pub started: bool,
+    state: watch::Receiver<RefreshState>,
+}
+
+impl RefreshJob {
+    pub fn state(&self) -> RefreshState {
+        self.state.borrow().clone()
+    }
+
+    /// Waits for the job to move on, None when it died without a report.
+    pub async fn next_state(&mut self) -> Option<RefreshState> {
+        if self.state.changed().await.is_err()
+            && !matches!(*self.state.borrow(), RefreshState::Done(_))
+        {
+            return None;
+        }
+
+        Some(self.state.borrow_and_update().clone())
+    }
+
+    pub async fn wait(mut self) -> Option<Arc<RefreshReport>> {
+        let mut state = self.state();
+        loop {
+            if let RefreshState::Done(report) = state {
+                return Some(report);
+            }
+            state = self.next_state().await?;
+        }
+    }
+}
+
+impl RefreshJobs {
+    pub fn new(store: SharedPlayerStore, source: Arc<dyn StatsSource>) -> Self {
+        RefreshJobs {
+            store,
+            source,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Starts a refresh, or joins the one already running.
+    pub async fn start(&self) -> RefreshJob {
+        let mut current = self.current.lock().await;
+        if let Some(running) = current.as_ref().filter(|r| !r.task.is_finished()) {
+            debug!("Refresh already running, joining it");
+            return RefreshJob {
+                started: false,
+                state: running.state.clone(),
+            };
+        }
+
+        let start = Instant::now();
+        let profiles = self.store.read().await.get_unique_profiles();
+        let (sender, receiver) = watch::channel(RefreshState::Running {
+            done: 0,
+            total: profiles.len(),
+        });
+
+        info!("Starting refresh of {} profiles", profiles.len());
+        let store = self.store.clone();
+        let source = self.source.clone();
+        let task = tokio::spawn(async move {
+            let total = profiles.len();
+            let on_fetched = |done| {
+                sender.send_replace(RefreshState::Running { done, total });
+            };
+            let fetches = source.get_players_stats(&profiles, &on_fetched).await;
+
+            let report = {
+                let mut store = store.write().await;
+                let report = store.apply_refresh(&profiles, fetches, start);
+                store.print();
+                report
+            };
+            sender.send_replace(RefreshState::Done(Arc::new(report)));
+        });
+
+        *current = Some(RunningRefresh {
+            task,
+            state: receiver.clone(),
+        });
+
+        RefreshJob {
+            started: true,
+            state: receiver,
+        }
+    }
+}
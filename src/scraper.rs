@@ -5,110 +5,258 @@ use chromiumoxide::{
     Browser, BrowserConfig, browser::HeadlessMode,
     cdp::browser_protocol::network::EventLoadingFinished,
 };
-use tokio::time::{Duration, sleep, timeout};
+use tokio::time::{Duration, Instant, sleep, timeout, timeout_at};
 
+use async_trait::async_trait;
 use chromiumoxide::cdp::browser_protocol::network::GetResponseBodyParams;
-use futures::StreamExt;
+use futures::{StreamExt, stream};
 use log::{debug, error, info};
 
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use rand::Rng;
+
+use crate::circuit_breaker::{CircuitBreaker, CircuitStatus};
+use crate::config::Config;
 use crate::model::player_stat::UggPlayerStat;
-use crate::player_store::{PlayerWithStats, RegisteredPlayer};
+use crate::player_store::RegisteredPlayer;
+use crate::stats_source::{
+    OnFetched, PlayerFetch, StatsSource, archive::PayloadArchive, parse_player_stat,
+};
+
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+const LAUNCH_HEALTH_CHECK_ATTEMPTS: u32 = 10;
 
+/// A Chromium instance along with the task driving its CDP connection
+struct RunningBrowser {
+    browser: Arc<Browser>,
+    handler_task: JoinHandle<()>,
+}
+
+/// Scrapes u.gg profile pages with a headless browser that is launched on first use
+/// and kept alive between refreshes.
 pub struct Scraper {
-    browser: Browser,
-    pub handler_task: tokio::task::JoinHandle<()>,
+    running: Mutex<Option<RunningBrowser>>,
+
+    /// How many profile pages are opened at the same time
+    concurrency: usize,
+    /// Overall time allowed to scrape a single player
+    player_timeout: Duration,
+    /// How many more attempts a player gets after a transient failure
+    retries: u32,
+    /// Delay before the first retry, doubled on each following one
+    retry_base_delay: Duration,
+    circuit_breaker: CircuitBreaker,
+    payload_archive: PayloadArchive,
 }
 
 #[derive(Debug)]
 pub enum ScrapeError {
     PageInit(String),
+    /// The profile page did not call the u.gg profile API
     RequestNotFound,
+    /// u.gg does not know the profile
+    ProfileNotFound,
     RequestContent,
     Timeout,
+    /// The payload does not match the expected u.gg schema
+    Parse(serde_json::Error),
+    /// u.gg failed too many times in a row, it is left alone for a while
+    CircuitOpen,
+}
+
+impl ScrapeError {
+    /// Whether trying again a bit later has a chance to succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ScrapeError::PageInit(_)
+            | ScrapeError::RequestNotFound
+            | ScrapeError::RequestContent
+            | ScrapeError::Timeout => true,
+            ScrapeError::ProfileNotFound | ScrapeError::Parse(_) | ScrapeError::CircuitOpen => {
+                false
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum ScraperInitError {
     Browser(String),
+    Http(String),
 }
 
 impl Scraper {
-    pub async fn new() -> Result<Self, ScraperInitError> {
+    pub fn new(config: &Config) -> Self {
+        Scraper {
+            running: Mutex::new(None),
+            concurrency: config.scraper_concurrency.max(1),
+            player_timeout: Duration::from_secs(config.scraper_player_timeout_second),
+            retries: config.scraper_retries,
+            retry_base_delay: Duration::from_millis(config.scraper_retry_base_delay_ms),
+            circuit_breaker: CircuitBreaker::new(
+                config.circuit_breaker_threshold,
+                Duration::from_secs(config.circuit_breaker_cooldown_minute as u64 * 60),
+            ),
+            payload_archive: PayloadArchive::new(config.ugg_debug_dir.as_deref()),
+        }
+    }
+
+    async fn launch() -> Result<RunningBrowser, ScraperInitError> {
         info!("Browser creation");
-        let config = BrowserConfig::builder()
+        let browser_config = BrowserConfig::builder()
             .headless_mode(HeadlessMode::True)
             .args(["--no-sandbox", "--disable-dev-shm-usage"])
             .build()
             .map_err(|e| ScraperInitError::Browser(e.to_string()))?;
 
-        let (browser, mut handler) = Browser::launch(config)
+        let (browser, mut handler) = Browser::launch(browser_config)
             .await
             .map_err(|e| ScraperInitError::Browser(e.to_string()))?;
 
+        let handler_task = tokio::spawn(async move { while handler.next().await.is_some() {} });
+
         debug!("Browser launched");
 
+        let running = RunningBrowser {
+            browser: Arc::new(browser),
+            handler_task,
+        };
+
         // Making sure the browser is ready for stuff
-        sleep(std::time::Duration::from_secs(5)).await;
+        for _ in 0..LAUNCH_HEALTH_CHECK_ATTEMPTS {
+            if running.is_healthy().await {
+                return Ok(running);
+            }
+            sleep(Duration::from_millis(500)).await;
+        }
 
-        let handler_task = tokio::spawn(async move { while let Some(_) = handler.next().await {} });
+        running.handler_task.abort();
+        Err(ScraperInitError::Browser(String::from(
+            "Browser did not answer after launch",
+        )))
+    }
 
-        let my_browser = Scraper {
-            browser,
-            handler_task,
-        };
+    /// Returns the running browser, launching or relaunching it when it is not healthy.
+    async fn browser(&self) -> Result<Arc<Browser>, ScraperInitError> {
+        let mut running = self.running.lock().await;
+
+        if let Some(current) = running.as_ref() {
+            if current.is_healthy().await {
+                return Ok(current.browser.clone());
+            }
+
+            error!("Browser is not responding anymore, relaunching it");
+            if let Some(dead) = running.take() {
+                dead.handler_task.abort();
+            }
+        }
 
-        Ok(my_browser)
+        let launched = Scraper::launch().await?;
+        let browser = launched.browser.clone();
+        *running = Some(launched);
+
+        Ok(browser)
     }
 
-    // TODO better error handling
-    pub async fn get_players_stats(
-        &mut self,
-        registered_players: &Vec<RegisteredPlayer>,
-    ) -> Vec<PlayerWithStats> {
-        let mut players_stats: Vec<PlayerWithStats> = vec![];
-        for player in registered_players {
-            if let Ok(player_stat) = self.get_player_stats(&player.rematch_url).await {
-                players_stats.push(PlayerWithStats {
-                    discord_id: player.discord_id,
-                    display_name: player_stat.player.display_name,
-                    rank: player_stat.rank,
-                    stats: player_stat.lifetime_stats,
-                });
-            } else {
-                error!("Failed to fetch stats for {}", player.discord_id);
+    /// Scrapes a profile page, retrying transient failures with an exponential backoff as long
+    /// as the player timeout allows it.
+    async fn get_page_stats_with_retry(
+        &self,
+        browser: &Browser,
+        player: &RegisteredPlayer,
+    ) -> Result<UggPlayerStat, ScrapeError> {
+        let url = &player.rematch_url;
+        let deadline = Instant::now() + self.player_timeout;
+        if !self.circuit_breaker.allow_request() {
+            return Err(ScrapeError::CircuitOpen);
+        }
+
+        // Retries belong to the same request, a half open probe keeps going
+        let mut attempt = 0;
+        let result = loop {
+            match self.get_page_payload(browser, url, deadline).await {
+                Ok(body) => break Ok(body),
+                Err(e) if e.is_transient() && attempt < self.retries => {
+                    let delay = self.retry_delay(attempt);
+                    if Instant::now() + delay >= deadline {
+                        break Err(e);
+                    }
+                    if self.circuit_breaker.status().is_open() {
+                        return Err(ScrapeError::CircuitOpen);
+                    }
+                    attempt += 1;
+                    info!(
+                        "Retry {}/{} for url={} in {}ms after {:?}",
+                        attempt,
+                        self.retries,
+                        url,
+                        delay.as_millis(),
+                        e
+                    );
+                    sleep(delay).await;
+                }
+                Err(e) => break Err(e),
             }
+        };
+
+        match result {
+            Ok(_) => self.circuit_breaker.record_success(),
+            Err(_) => self.circuit_breaker.record_failure(),
         }
 
-        players_stats
+        let body = result?;
+        self.payload_archive.save(&player.rematch_url, &body).await;
+
+        parse_player_stat(&body)
     }
 
-    async fn get_player_stats(&mut self, url: &str) -> Result<UggPlayerStat, ScrapeError> {
+    /// `base * 2^attempt`, plus up to `base` of jitter so parallel retries do not line up.
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let base = self.retry_base_delay.as_millis() as u64;
+        let backoff = base.saturating_mul(1 << attempt.min(10));
+        let jitter = rand::rng().random_range(0..=base);
+
+        Duration::from_millis(backoff + jitter)
+    }
+
+    /// Opens the profile page and returns the body of the u.gg profile API call it makes.
+    async fn get_page_payload(
+        &self,
+        browser: &Browser,
+        url: &str,
+        deadline: Instant,
+    ) -> Result<String, ScrapeError> {
         debug!("get_player_stats - start for url={}", url);
-        let page = self
-            .browser
-            .new_page("about:blank")
-            .await
-            .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
 
-        page.execute(NetworkEnableParams::default())
+        let page = timeout_at(deadline, browser.new_page("about:blank"))
             .await
+            .map_err(|_| ScrapeError::Timeout)?
             .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
 
-        let mut responses = page
-            .event_listener::<EventRequestWillBeSent>()
-            .await
-            .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
-        let mut finished_events = page
-            .event_listener::<EventLoadingFinished>()
-            .await
-            .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
+        // The page is always closed afterwards, even when the deadline is reached
+        let result = timeout_at(deadline, async {
+            page.execute(NetworkEnableParams::default())
+                .await
+                .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
 
-        page.goto(url)
-            .await
-            .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
-        debug!("get_player_stats - browser on page url={}", url);
+            let mut responses = page
+                .event_listener::<EventRequestWillBeSent>()
+                .await
+                .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
+            let mut finished_events = page
+                .event_listener::<EventLoadingFinished>()
+                .await
+                .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
+
+            page.goto(url)
+                .await
+                .map_err(|e| ScrapeError::PageInit(e.to_string()))?;
+            debug!("get_player_stats - browser on page url={}", url);
 
-        let result = async {
             let mut api_profile_request_id = None;
             while let Ok(Some(event)) = timeout(Duration::from_secs(10), responses.next()).await {
                 let method = &event.request.method;
@@ -142,17 +290,117 @@ impl Scraper {
                     let body = response_body.body.clone();
                     debug!("event {:?} content: {}", event.request_id, body);
 
-                    let player_stat: UggPlayerStat = serde_json::from_str(&body).unwrap();
-                    return Ok(player_stat);
+                    return Ok(body);
                 }
             }
 
-            return Err(ScrapeError::Timeout);
-        }
-        .await;
+            Err(ScrapeError::Timeout)
+        })
+        .await
+        .unwrap_or(Err(ScrapeError::Timeout));
 
         let _ = page.close().await;
 
         result
     }
 }
+
+#[async_trait]
+impl StatsSource for Scraper {
+    async fn get_player_stats(
+        &self,
+        player: &RegisteredPlayer,
+    ) -> Result<UggPlayerStat, ScrapeError> {
+        let browser = self
+            .browser()
+            .await
+            .map_err(|e| ScrapeError::PageInit(format!("{:?}", e)))?;
+
+        self.get_page_stats_with_retry(&browser, player).await
+    }
+
+    async fn get_players_stats(
+        &self,
+        registered_players: &[RegisteredPlayer],
+        on_fetched: OnFetched<'_>,
+    ) -> Vec<PlayerFetch> {
+        let browser = match self.browser().await {
+            Ok(browser) => browser,
+            Err(e) => {
+                error!("Browser unavailable, no player refreshed, {:?}", e);
+                return registered_players
+                    .iter()
+                    .map(|player| {
+                        let error = ScrapeError::PageInit(format!("{:?}", e));
+                        PlayerFetch::new(player, Err(error), Duration::ZERO)
+                    })
+                    .collect();
+            }
+        };
+
+        let browser = &browser;
+        let scrapes: Vec<_> = registered_players
+            .iter()
+            .enumerate()
+            .map(|(position, player)| async move {
+                let start = Instant::now();
+                let result = self.get_page_stats_with_retry(browser, player).await;
+                (position, PlayerFetch::new(player, result, start.elapsed()))
+            })
+            .collect();
+
+        // Taken as they complete so that a slow player holds back neither the others nor the
+        // progress, then put back in the order of the registered players
+        let mut fetches: Vec<(usize, PlayerFetch)> = stream::iter(scrapes)
+            .buffer_unordered(self.concurrency)
+            .enumerate()
+            .map(|(i, fetch)| {
+                on_fetched(i + 1);
+                fetch
+            })
+            .collect()
+            .await;
+        fetches.sort_by_key(|(position, _)| *position);
+
+        fetches.into_iter().map(|(_, fetch)| fetch).collect()
+    }
+
+    fn circuit_status(&self) -> Option<CircuitStatus> {
+        Some(self.circuit_breaker.status())
+    }
+
+    async fn shutdown(&self) {
+        let running = self.running.lock().await.take();
+        let Some(running) = running else {
+            return;
+        };
+
+        info!("Closing browser");
+        match Arc::try_unwrap(running.browser) {
+            Ok(mut browser) => {
+                if let Err(e) = browser.close().await {
+                    error!("Could not close browser, {}", e);
+                }
+                let _ = browser.wait().await;
+            }
+            // A scrape is still using it, dropping the last reference kills the process
+            Err(_) => debug!("Browser still in use, it will be killed on drop"),
+        }
+
+        running.handler_task.abort();
+    }
+}
+
+impl RunningBrowser {
+    /// A trivial CDP call, failing when the browser crashed or hangs.
+    async fn is_healthy(&self) -> bool {
+        if self.handler_task.is_finished() {
+            return false;
+        }
+
+        matches!(
+            timeout(HEALTH_CHECK_TIMEOUT, self.browser.version()).await,
+            Ok(Ok(_))
+        )
+    }
+}
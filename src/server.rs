@@ -3,12 +3,14 @@ use std::sync::Arc;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome, Request};
 
-use rocket::serde::{Deserialize, json::Json};
+use rocket::serde::{Deserialize, Serialize, json::Json};
 use rocket::{Build, Rocket};
-use rocket::{post, routes};
+use rocket::{get, post, routes};
 
+use crate::circuit_breaker::CircuitStatus;
 use crate::config::Config;
-use crate::discord::play_marius;
+use crate::stats_source::StatsSource;
+use crate::{discord::play_marius, locale::Locale};
 use tokio::sync::RwLock;
 
 use rocket::State;
@@ -56,16 +58,36 @@ async fn marius(
 
     match &*ctx_lock {
         Some(ctx) => {
-            let _ = play_marius(ctx, body.guild_id.into(), body.user_id.into()).await;
+            let _ = play_marius(
+                ctx,
+                body.guild_id.into(),
+                body.user_id.into(),
+                Locale::default(),
+            )
+            .await;
             "Success"
         }
         None => "Fail",
     }
 }
 
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct StatusBody {
+    circuit_breaker: Option<CircuitStatus>,
+}
+
+#[get("/status")]
+async fn status(_key: ApiKey, stats_source: &State<Arc<dyn StatsSource>>) -> Json<StatusBody> {
+    Json(StatusBody {
+        circuit_breaker: stats_source.circuit_status(),
+    })
+}
+
 pub fn start_http_server(
     my_config: &Config,
     discord_ctx: Arc<RwLock<Option<Arc<serenity::prelude::Context>>>>,
+    stats_source: Arc<dyn StatsSource>,
 ) -> Rocket<Build> {
     let config = rocket::Config {
         port: my_config.http_port,
@@ -75,5 +97,6 @@ pub fn start_http_server(
     rocket::custom(config)
         .manage(my_config.clone())
         .manage(discord_ctx)
-        .mount("/", routes![marius])
+        .manage(stats_source)
+        .mount("/", routes![marius, status])
 }
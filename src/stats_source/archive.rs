@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use log::{debug, error, info};
+
+use crate::{model::rematch_profile::RematchProfile, storage::TIMESTAMP_FORMAT};
+
+/// Keeps every raw u.gg payload in a debug directory, one file per account and fetch.
+pub struct PayloadArchive {
+    dir: Option<PathBuf>,
+}
+
+impl PayloadArchive {
+    pub fn new(dir: Option<&str>) -> Self {
+        PayloadArchive {
+            dir: dir.map(PathBuf::from),
+        }
+    }
+
+    pub async fn save(&self, rematch_url: &str, body: &str) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let profile = match RematchProfile::parse(rematch_url) {
+            Ok(profile) => profile,
+            Err(e) => {
+                error!("Could not archive payload of {}, {:?}", rematch_url, e);
+                return;
+            }
+        };
+
+        if let Err(e) = tokio::fs::create_dir_all(dir).await {
+            error!("Could not create {} directory, {}", dir.display(), e);
+            return;
+        }
+
+        let timestamp = Utc::now().format(TIMESTAMP_FORMAT);
+        let path = dir.join(format!("{}-{}.json", profile.file_stem(), timestamp));
+        match tokio::fs::write(&path, body).await {
+            Ok(_) => debug!("Payload archived in {}", path.display()),
+            Err(e) => error!("Could not write {} file, {}", path.display(), e),
+        }
+    }
+
+    /// Deletes every payload archived for these accounts.
+    pub async fn remove(&self, rematch_urls: &[String]) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let prefixes: Vec<String> = rematch_urls
+            .iter()
+            .filter_map(|rematch_url| RematchProfile::parse(rematch_url).ok())
+            .map(|profile| format!("{}-", profile.file_stem()))
+            .collect();
+        if prefixes.is_empty() {
+            return;
+        }
+
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Could not list {} directory, {}", dir.display(), e);
+                return;
+            }
+        };
+
+        let mut removed_count = 0;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let is_archived = prefixes.iter().any(|prefix| {
+                name.strip_prefix(prefix)
+                    .is_some_and(|timestamp| !timestamp.contains('-') && name.ends_with(".json"))
+            });
+            if !is_archived {
+                continue;
+            }
+
+            match tokio::fs::remove_file(entry.path()).await {
+                Ok(_) => removed_count += 1,
+                Err(e) => error!("Could not remove {} file, {}", entry.path().display(), e),
+            }
+        }
+        info!("{} archived payloads removed", removed_count);
+    }
+}
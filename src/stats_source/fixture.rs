@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use log::debug;
+
+use crate::{
+    model::{player_stat::UggPlayerStat, rematch_profile::RematchProfile},
+    player_store::RegisteredPlayer,
+    scraper::ScrapeError,
+};
+
+use super::{StatsSource, parse_player_stat};
+
+/// Reads `{platform}-{platform_id}.json` u.gg payloads from a directory, e.g.
+/// `steam-76561198355389674.json`, handy to work offline.
+pub struct FixtureSource {
+    dir: PathBuf,
+}
+
+impl FixtureSource {
+    pub fn new(dir: &str) -> Self {
+        FixtureSource {
+            dir: PathBuf::from(dir),
+        }
+    }
+}
+
+#[async_trait]
+impl StatsSource for FixtureSource {
+    async fn get_player_stats(
+        &self,
+        player: &RegisteredPlayer,
+    ) -> Result<UggPlayerStat, ScrapeError> {
+        let profile = RematchProfile::parse(&player.rematch_url).map_err(|e| {
+            ScrapeError::PageInit(format!("Unexpected url {}, {:?}", player.rematch_url, e))
+        })?;
+        let path = self.dir.join(format!("{}.json", profile.file_stem()));
+        debug!("get_player_stats - reading fixture {}", path.display());
+
+        let body = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|_| ScrapeError::ProfileNotFound)?;
+
+        parse_player_stat(&body)
+    }
+}
@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use log::debug;
+
+use crate::{
+    config::Config,
+    model::{player_stat::UggPlayerStat, rematch_profile::RematchProfile},
+    player_store::RegisteredPlayer,
+    scraper::{ScrapeError, ScraperInitError},
+};
+
+use super::{StatsSource, archive::PayloadArchive, parse_player_stat};
+
+/// Calls the u.gg profiles API directly, without rendering the profile page.
+pub struct HttpSource {
+    client: reqwest::Client,
+    api_url: String,
+    payload_archive: PayloadArchive,
+}
+
+impl HttpSource {
+    pub fn new(config: &Config) -> Result<Self, ScraperInitError> {
+        let client = reqwest::Client::builder()
+            .user_agent("Mozilla/5.0 (X11; Linux x86_64) rebot")
+            .timeout(std::time::Duration::from_secs(20))
+            .build()
+            .map_err(|e| ScraperInitError::Http(e.to_string()))?;
+
+        Ok(HttpSource {
+            client,
+            api_url: config.ugg_api_url.trim_end_matches('/').to_owned(),
+            payload_archive: PayloadArchive::new(config.ugg_debug_dir.as_deref()),
+        })
+    }
+
+    /// The API only needs the platform and its id from the profile URL.
+    fn api_profile_url(&self, rematch_url: &str) -> Result<String, ScrapeError> {
+        let profile = RematchProfile::parse(rematch_url).map_err(|e| {
+            ScrapeError::PageInit(format!("Unexpected url {}, {:?}", rematch_url, e))
+        })?;
+
+        Ok(format!(
+            "{}/{}/{}",
+            self.api_url,
+            profile.platform.ugg_key(),
+            profile.platform_id
+        ))
+    }
+}
+
+#[async_trait]
+impl StatsSource for HttpSource {
+    async fn get_player_stats(
+        &self,
+        player: &RegisteredPlayer,
+    ) -> Result<UggPlayerStat, ScrapeError> {
+        let url = self.api_profile_url(&player.rematch_url)?;
+        debug!("get_player_stats - calling url={}", url);
+
+        let response = self.client.get(&url).send().await.map_err(|e| {
+            if e.is_timeout() {
+                ScrapeError::Timeout
+            } else {
+                ScrapeError::PageInit(e.to_string())
+            }
+        })?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ScrapeError::ProfileNotFound);
+        }
+
+        let body = response
+            .error_for_status()
+            .map_err(|_| ScrapeError::RequestContent)?
+            .text()
+            .await
+            .map_err(|_| ScrapeError::RequestContent)?;
+        debug!("url {} content: {}", url, body);
+
+        self.payload_archive.save(&player.rematch_url, &body).await;
+
+        parse_player_stat(&body)
+    }
+}
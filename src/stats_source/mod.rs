@@ -0,0 +1,102 @@
+pub mod archive;
+pub mod fixture;
+pub mod http;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use log::error;
+
+use crate::{
+    circuit_breaker::CircuitStatus,
+    config::{Config, StatsSourceKind},
+    model::player_stat::UggPlayerStat,
+    player_store::RegisteredPlayer,
+    scraper::{ScrapeError, Scraper, ScraperInitError},
+};
+
+use self::{fixture::FixtureSource, http::HttpSource};
+
+/// Outcome of fetching the stats of a single registered player.
+#[derive(Debug)]
+pub struct PlayerFetch {
+    pub discord_id: u64,
+    pub result: Result<UggPlayerStat, ScrapeError>,
+    pub duration: Duration,
+}
+
+impl PlayerFetch {
+    pub fn new(
+        player: &RegisteredPlayer,
+        result: Result<UggPlayerStat, ScrapeError>,
+        duration: Duration,
+    ) -> Self {
+        if let Err(e) = &result {
+            error!("Failed to fetch stats for {}, {:?}", player.discord_id, e);
+        }
+
+        PlayerFetch {
+            discord_id: player.discord_id,
+            result,
+            duration,
+        }
+    }
+}
+
+/// Progress callback of a refresh, called with the number of players fetched so far.
+pub type OnFetched<'a> = &'a (dyn Fn(usize) + Send + Sync);
+
+/// Somewhere u.gg player stats can be fetched from.
+#[async_trait]
+pub trait StatsSource: Send + Sync {
+    async fn get_player_stats(
+        &self,
+        player: &RegisteredPlayer,
+    ) -> Result<UggPlayerStat, ScrapeError>;
+
+    /// Fetches in the order of `registered_players`, `on_fetched` is given how many are done so far.
+    async fn get_players_stats(
+        &self,
+        registered_players: &[RegisteredPlayer],
+        on_fetched: OnFetched<'_>,
+    ) -> Vec<PlayerFetch> {
+        let mut fetches: Vec<PlayerFetch> = vec![];
+        for player in registered_players {
+            let start = Instant::now();
+            let result = self.get_player_stats(player).await;
+            fetches.push(PlayerFetch::new(player, result, start.elapsed()));
+            on_fetched(fetches.len());
+        }
+
+        fetches
+    }
+
+    /// State of the circuit breaker protecting u.gg, for sources having one.
+    fn circuit_status(&self) -> Option<CircuitStatus> {
+        None
+    }
+
+    /// Releases anything held between refreshes, called once on process exit.
+    async fn shutdown(&self) {}
+}
+
+pub fn build_stats_source(config: &Config) -> Result<Arc<dyn StatsSource>, ScraperInitError> {
+    let source: Arc<dyn StatsSource> = match config.stats_source {
+        StatsSourceKind::Browser => Arc::new(Scraper::new(config)),
+        StatsSourceKind::Http => Arc::new(HttpSource::new(config)?),
+        StatsSourceKind::Fixture => Arc::new(FixtureSource::new(&config.fixture_dir)),
+    };
+
+    Ok(source)
+}
+
+/// Parses a u.gg profile payload, a failure usually means u.gg changed its API.
+pub fn parse_player_stat(body: &str) -> Result<UggPlayerStat, ScrapeError> {
+    serde_json::from_str(body).map_err(|e| {
+        error!("Could not parse u.gg payload, {}", e);
+        ScrapeError::Parse(e)
+    })
+}
@@ -0,0 +1,191 @@
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use log::{error, info, warn};
+
+use crate::player_store::PlayerStore;
+
+use super::{
+    Storage, StorageError, TIMESTAMP_FORMAT,
+    migration::{MigrationContext, parse_store, to_versioned_value},
+};
+
+/// The whole store in a single pretty printed JSON file.
+///
+/// Writes go through a temporary file renamed over the database, and the previous versions
+/// are kept as timestamped backups to recover from a corrupt file.
+#[derive(Debug)]
+pub struct JsonStorage {
+    path: PathBuf,
+    backup_count: usize,
+    migration_context: MigrationContext,
+}
+
+impl JsonStorage {
+    pub fn new(path: &str, backup_count: usize, migration_context: MigrationContext) -> Self {
+        JsonStorage {
+            path: PathBuf::from(path),
+            backup_count,
+            migration_context,
+        }
+    }
+
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        self.path
+            .with_file_name(format!("{}.{}", self.file_name(), suffix))
+    }
+
+    /// Backups of the database, newest first.
+    fn list_backups(&self) -> Vec<PathBuf> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let prefix = format!("{}.", self.file_name());
+
+        let mut backups: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    name.starts_with(&prefix) && name.ends_with(".bak")
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        // Timestamps are fixed width, sorting names sorts dates
+        backups.sort();
+        backups.reverse();
+        backups
+    }
+
+    fn backup_current(&self) -> Result<(), StorageError> {
+        if self.backup_count == 0 || !self.path.exists() {
+            return Ok(());
+        }
+
+        let backup_path = self.sibling(&format!("{}.bak", timestamp()));
+        fs::copy(&self.path, &backup_path).map_err(|e| StorageError::Io(e.to_string()))?;
+
+        for old_backup in self.list_backups().iter().skip(self.backup_count) {
+            if let Err(e) = fs::remove_file(old_backup) {
+                warn!("Could not remove {} backup, {}", old_backup.display(), e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves a corrupt database aside so that it is never overwritten.
+    fn quarantine(&self) -> Result<(), StorageError> {
+        let quarantine_path = self.sibling(&format!("corrupt-{}", timestamp()));
+        fs::rename(&self.path, &quarantine_path).map_err(|e| {
+            StorageError::Io(format!(
+                "Could not quarantine corrupt {}, {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+        error!("Corrupt database moved to {}", quarantine_path.display());
+
+        Ok(())
+    }
+
+    fn load_newest_backup(&self) -> Option<PlayerStore> {
+        for backup in self.list_backups() {
+            let store = fs::read_to_string(&backup)
+                .ok()
+                .and_then(|json_data| parse_json_store(&json_data, &self.migration_context).ok());
+
+            match store {
+                Some(store) => {
+                    info!("Database restored from {}", backup.display());
+                    return Some(store);
+                }
+                None => warn!("Backup {} is not readable either", backup.display()),
+            }
+        }
+
+        None
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load(&self) -> Result<Option<PlayerStore>, StorageError> {
+        let json_data = match fs::read_to_string(&self.path) {
+            Ok(json_data) => json_data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(StorageError::Io(e.to_string())),
+        };
+
+        match parse_json_store(&json_data, &self.migration_context) {
+            Ok(store) => Ok(Some(store)),
+            // A newer bot wrote it, the file is fine but we cannot read it
+            Err(StorageError::Migration(e)) => Err(StorageError::Migration(e)),
+            Err(e) => {
+                error!("Could not parse {} database, {:?}", self.path.display(), e);
+                // A backup is only worth loading if the next save cannot overwrite the original
+                self.quarantine()?;
+
+                match self.load_newest_backup() {
+                    Some(store) => Ok(Some(store)),
+                    None => {
+                        error!("No valid backup found, starting from an empty database");
+                        Ok(None)
+                    }
+                }
+            }
+        }
+    }
+
+    fn save(&self, store: &PlayerStore) -> Result<(), StorageError> {
+        let json_data = serde_json::to_string_pretty(&to_versioned_value(store)?)
+            .map_err(|e| StorageError::Parse(e.to_string()))?;
+
+        let tmp_path = self.sibling("tmp");
+        let mut file = File::create(&tmp_path).map_err(|e| StorageError::Io(e.to_string()))?;
+        file.write_all(json_data.as_bytes())
+            .and_then(|_| file.sync_all())
+            .map_err(|e| StorageError::Io(e.to_string()))?;
+
+        self.backup_current()?;
+
+        fs::rename(&tmp_path, &self.path).map_err(|e| StorageError::Io(e.to_string()))?;
+
+        // Persisting the rename itself, not supported everywhere hence best effort
+        let dir = self.path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        if let Some(Ok(dir)) = dir.map(File::open) {
+            let _ = dir.sync_all();
+        }
+
+        Ok(())
+    }
+}
+
+pub fn parse_json_store(
+    json_data: &str,
+    context: &MigrationContext,
+) -> Result<PlayerStore, StorageError> {
+    let value = serde_json::from_str(json_data).map_err(|e| StorageError::Parse(e.to_string()))?;
+    parse_store(value, context)
+}
+
+fn timestamp() -> String {
+    Utc::now().format(TIMESTAMP_FORMAT).to_string()
+}
@@ -0,0 +1,293 @@
+use log::{info, warn};
+use serde_json::{Value, json};
+
+use crate::{config::Config, model::rematch_profile::RematchProfile, player_store::PlayerStore};
+
+use super::StorageError;
+
+/// Version written along with the store, bump it with every new migration below.
+pub const SCHEMA_VERSION: u32 = 5;
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// What the migrations cannot guess from the stored data alone.
+#[derive(Debug, Default, Clone)]
+pub struct MigrationContext {
+    /// Guild the players registered before multi-guild support belong to
+    pub default_guild_id: Option<u64>,
+}
+
+impl MigrationContext {
+    pub fn new(config: &Config) -> Self {
+        MigrationContext {
+            default_guild_id: config.default_guild_id,
+        }
+    }
+}
+
+type Migration = fn(Value, &MigrationContext) -> Result<Value, StorageError>;
+
+/// `MIGRATIONS[n]` upgrades a store from version `n + 1` to version `n + 2`.
+const MIGRATIONS: [Migration; (SCHEMA_VERSION - 1) as usize] = [
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+];
+
+/// Files written before versioning have no `schema_version`, they are version 1.
+pub fn get_schema_version(value: &Value) -> Result<u32, StorageError> {
+    match value.get(SCHEMA_VERSION_KEY) {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .map(|version| version as u32)
+            .ok_or_else(|| StorageError::Parse(format!("Invalid schema version {}", version))),
+    }
+}
+
+/// Upgrades a serialized store step by step up to the current version.
+pub fn migrate(mut value: Value, context: &MigrationContext) -> Result<Value, StorageError> {
+    let mut version = get_schema_version(&value)?;
+
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(StorageError::Migration(format!(
+            "Schema version {} is not supported, expected at most {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+
+    while version < SCHEMA_VERSION {
+        info!(
+            "Migrating database from version {} to {}",
+            version,
+            version + 1
+        );
+        value = MIGRATIONS[version as usize - 1](value, context)?;
+        version += 1;
+        set_schema_version(&mut value, version)?;
+    }
+
+    Ok(value)
+}
+
+/// Parses and migrates a serialized store, whatever its version.
+pub fn parse_store(value: Value, context: &MigrationContext) -> Result<PlayerStore, StorageError> {
+    let value = migrate(value, context)?;
+    serde_json::from_value(value).map_err(|e| StorageError::Parse(e.to_string()))
+}
+
+pub fn to_versioned_value(store: &PlayerStore) -> Result<Value, StorageError> {
+    let mut value = serde_json::to_value(store).map_err(|e| StorageError::Parse(e.to_string()))?;
+    set_schema_version(&mut value, SCHEMA_VERSION)?;
+
+    Ok(value)
+}
+
+fn set_schema_version(value: &mut Value, version: u32) -> Result<(), StorageError> {
+    let object = as_object_mut(value)?;
+    object.insert(String::from(SCHEMA_VERSION_KEY), json!(version));
+
+    Ok(())
+}
+
+/// Version 2 introduced the stats history and the guild settings.
+fn migrate_v1_to_v2(mut value: Value, _: &MigrationContext) -> Result<Value, StorageError> {
+    let object = as_object_mut(&mut value)?;
+
+    object
+        .entry("history")
+        .or_insert_with(|| json!({ "snapshots": {} }));
+    object.entry("guild_settings").or_insert_with(|| json!([]));
+
+    Ok(value)
+}
+
+/// Version 3 scoped registrations to a guild and keyed stats by u.gg profile.
+fn migrate_v2_to_v3(mut value: Value, context: &MigrationContext) -> Result<Value, StorageError> {
+    let object = as_object_mut(&mut value)?;
+
+    let mut registered_players = take_array(object, "registered_players");
+    if !registered_players.is_empty() && context.default_guild_id.is_none() {
+        return Err(StorageError::Migration(String::from(
+            "Players must be assigned to a guild, configure DEFAULT_GUILD_ID",
+        )));
+    }
+    for registered_player in registered_players.iter_mut() {
+        as_object_mut(registered_player)?
+            .insert(String::from("guild_id"), json!(context.default_guild_id));
+    }
+
+    let mut players = vec![];
+    for mut player in take_array(object, "players") {
+        let rematch_url = registered_players
+            .iter()
+            .find(|registered_player| registered_player["discord_id"] == player["discord_id"])
+            .map(|registered_player| registered_player["rematch_url"].clone());
+
+        match rematch_url {
+            Some(rematch_url) => {
+                as_object_mut(&mut player)?.insert(String::from("rematch_url"), rematch_url);
+                players.push(player);
+            }
+            None => warn!(
+                "Dropping stats of unregistered player {}",
+                player["discord_id"]
+            ),
+        }
+    }
+
+    object.insert(
+        String::from("registered_players"),
+        json!(registered_players),
+    );
+    object.insert(String::from("players"), json!(players));
+
+    Ok(value)
+}
+
+/// Version 4 allowed several accounts per user, the history is now kept per u.gg profile.
+fn migrate_v3_to_v4(mut value: Value, _: &MigrationContext) -> Result<Value, StorageError> {
+    let object = as_object_mut(&mut value)?;
+
+    // A single account per user until now
+    let mut registered_players = take_array(object, "registered_players");
+    for registered_player in registered_players.iter_mut() {
+        as_object_mut(registered_player)?.insert(String::from("main"), json!(true));
+    }
+    object.insert(
+        String::from("registered_players"),
+        json!(registered_players),
+    );
+
+    let players = object.get("players").cloned().unwrap_or(json!([]));
+    let Some(Value::Object(snapshots)) = object
+        .get_mut("history")
+        .and_then(|history| history.get_mut("snapshots"))
+        .filter(|snapshots| snapshots.is_object())
+        .map(Value::take)
+    else {
+        return Ok(value);
+    };
+
+    let mut profile_snapshots = serde_json::Map::new();
+    for (discord_id, player_snapshots) in snapshots {
+        let id = discord_id.parse::<u64>().ok();
+        let rematch_url = players
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|player| id.is_some() && player["discord_id"].as_u64() == id)
+            .and_then(|player| player["rematch_url"].as_str());
+
+        match rematch_url {
+            Some(rematch_url) => {
+                profile_snapshots.insert(String::from(rematch_url), player_snapshots);
+            }
+            None => warn!("Dropping history of unregistered player {}", discord_id),
+        }
+    }
+    object.insert(
+        String::from("history"),
+        json!({ "snapshots": profile_snapshots }),
+    );
+
+    Ok(value)
+}
+
+/// Version 5 stored profile URLs in a single canonical spelling.
+fn migrate_v4_to_v5(mut value: Value, _: &MigrationContext) -> Result<Value, StorageError> {
+    let object = as_object_mut(&mut value)?;
+
+    let mut registered_players: Vec<Value> = vec![];
+    for mut registered_player in take_array(object, "registered_players") {
+        canonicalize_field(&mut registered_player)?;
+        let is_duplicate = registered_players.iter().any(|p| {
+            p["guild_id"] == registered_player["guild_id"]
+                && p["discord_id"] == registered_player["discord_id"]
+                && p["rematch_url"] == registered_player["rematch_url"]
+        });
+        if !is_duplicate {
+            registered_players.push(registered_player);
+        }
+    }
+    object.insert(
+        String::from("registered_players"),
+        json!(registered_players),
+    );
+
+    let mut players: Vec<Value> = vec![];
+    for mut player in take_array(object, "players") {
+        canonicalize_field(&mut player)?;
+        if !players
+            .iter()
+            .any(|p| p["rematch_url"] == player["rematch_url"])
+        {
+            players.push(player);
+        }
+    }
+    object.insert(String::from("players"), json!(players));
+
+    let Some(Value::Object(snapshots)) = object
+        .get_mut("history")
+        .and_then(|history| history.get_mut("snapshots"))
+        .filter(|snapshots| snapshots.is_object())
+        .map(Value::take)
+    else {
+        return Ok(value);
+    };
+
+    let mut profile_snapshots = serde_json::Map::new();
+    for (rematch_url, player_snapshots) in snapshots {
+        let merged = profile_snapshots
+            .entry(canonical_url(&rematch_url))
+            .or_insert_with(|| json!([]));
+        if let (Some(merged), Value::Array(player_snapshots)) =
+            (merged.as_array_mut(), player_snapshots)
+        {
+            merged.extend(player_snapshots);
+            // Same timestamp format everywhere, sorting text sorts dates
+            merged.sort_by(|a, b| a["taken_at"].as_str().cmp(&b["taken_at"].as_str()));
+        }
+    }
+    object.insert(
+        String::from("history"),
+        json!({ "snapshots": profile_snapshots }),
+    );
+
+    Ok(value)
+}
+
+/// The canonical spelling of a profile URL, left untouched when it cannot be parsed.
+pub fn canonical_url(rematch_url: &str) -> String {
+    match RematchProfile::parse(rematch_url) {
+        Ok(profile) => profile.to_url(),
+        Err(e) => {
+            warn!("Keeping unexpected profile url {}, {:?}", rematch_url, e);
+            rematch_url.to_owned()
+        }
+    }
+}
+
+fn canonicalize_field(value: &mut Value) -> Result<(), StorageError> {
+    let object = as_object_mut(value)?;
+    if let Some(rematch_url) = object.get("rematch_url").and_then(Value::as_str) {
+        let rematch_url = canonical_url(rematch_url);
+        object.insert(String::from("rematch_url"), json!(rematch_url));
+    }
+
+    Ok(())
+}
+
+fn as_object_mut(value: &mut Value) -> Result<&mut serde_json::Map<String, Value>, StorageError> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| StorageError::Parse(String::from("The store is not a JSON object")))
+}
+
+fn take_array(object: &mut serde_json::Map<String, Value>, key: &str) -> Vec<Value> {
+    match object.remove(key) {
+        Some(Value::Array(values)) => values,
+        _ => vec![],
+    }
+}
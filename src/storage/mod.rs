@@ -0,0 +1,51 @@
+pub mod json;
+pub mod migration;
+pub mod sqlite;
+
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{
+    config::{Config, DatabaseBackend},
+    player_store::PlayerStore,
+};
+
+use self::{json::JsonStorage, migration::MigrationContext, sqlite::SqliteStorage};
+
+/// Timestamps in file names, sorted as they are written and without `-` nor `:`, e.g. backups
+/// and archived payloads.
+pub const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(String),
+    Parse(String),
+    /// The stored schema version cannot be upgraded to the current one
+    Migration(String),
+    Sqlite(String),
+}
+
+/// Where the player store is persisted between restarts.
+pub trait Storage: Debug + Send + Sync {
+    /// `None` when nothing was ever saved.
+    fn load(&self) -> Result<Option<PlayerStore>, StorageError>;
+
+    fn save(&self, store: &PlayerStore) -> Result<(), StorageError>;
+}
+
+pub fn build_storage(config: &Config) -> Result<Arc<dyn Storage>, StorageError> {
+    let migration_context = MigrationContext::new(config);
+    let storage: Arc<dyn Storage> = match config.database_backend {
+        DatabaseBackend::Json => Arc::new(JsonStorage::new(
+            &config.database_path,
+            config.database_backup_count,
+            migration_context,
+        )),
+        DatabaseBackend::Sqlite => Arc::new(SqliteStorage::open(
+            &config.database_path,
+            config.database_import_path.as_deref(),
+            migration_context,
+        )?),
+    };
+
+    Ok(storage)
+}
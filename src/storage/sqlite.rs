@@ -0,0 +1,430 @@
+use std::{fs, path::Path, sync::Mutex};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::{error, info};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::{
+    history::StatsSnapshot,
+    model::player_stat::{Division, League, UggRank},
+    player_store::PlayerStore,
+};
+
+use super::{
+    Storage, StorageError,
+    json::parse_json_store,
+    migration::{MigrationContext, SCHEMA_VERSION, canonical_url, parse_store},
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS registered_players (
+        position INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS players (
+        position INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS guild_settings (
+        position INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS user_settings (
+        position INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS snapshots (
+        discord_id INTEGER NOT NULL,
+        taken_at TEXT NOT NULL,
+        current_league INTEGER,
+        current_division INTEGER,
+        matches_played INTEGER NOT NULL,
+        wins INTEGER NOT NULL,
+        PRIMARY KEY (discord_id, taken_at)
+    );
+    CREATE TABLE IF NOT EXISTS profile_snapshots (
+        rematch_url TEXT NOT NULL,
+        taken_at TEXT NOT NULL,
+        current_league INTEGER,
+        current_division INTEGER,
+        matches_played INTEGER NOT NULL,
+        wins INTEGER NOT NULL,
+        PRIMARY KEY (rematch_url, taken_at)
+    );
+";
+
+/// Before version 4 snapshots were kept per discord id, they are moved to the profile the
+/// user had registered.
+const MIGRATE_SNAPSHOTS: &str = "
+    INSERT OR IGNORE INTO profile_snapshots
+        (rematch_url, taken_at, current_league, current_division, matches_played, wins)
+    SELECT rematch_url, taken_at, current_league, current_division, matches_played, wins
+    FROM (
+        SELECT (
+            SELECT json_extract(registered_players.data, '$.rematch_url')
+            FROM registered_players
+            WHERE json_extract(registered_players.data, '$.discord_id') = snapshots.discord_id
+            ORDER BY position
+            LIMIT 1
+        ) AS rematch_url, *
+        FROM snapshots
+    )
+    WHERE rematch_url IS NOT NULL;
+    DELETE FROM snapshots;
+";
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+/// Set once the database was created, along with the JSON database imported if any
+const INITIALIZED_KEY: &str = "initialized_from";
+
+/// An embedded SQLite database. Small collections are stored as JSON rows and rewritten on
+/// save, stats snapshots get their own table and are only appended.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    import_path: Option<String>,
+    migration_context: MigrationContext,
+}
+
+impl SqliteStorage {
+    pub fn open(
+        path: &str,
+        import_path: Option<&str>,
+        migration_context: MigrationContext,
+    ) -> Result<Self, StorageError> {
+        let connection = Connection::open(path).map_err(to_storage_error)?;
+        connection.execute_batch(SCHEMA).map_err(to_storage_error)?;
+
+        Ok(SqliteStorage {
+            connection: Mutex::new(connection),
+            import_path: import_path.map(String::from),
+            migration_context,
+        })
+    }
+
+    /// Copies an existing JSON database the first time the SQLite one is used, the JSON file is
+    /// only read, even when it does not parse.
+    fn import_json(&self) -> Result<Option<PlayerStore>, StorageError> {
+        let import_path = self
+            .import_path
+            .as_deref()
+            .filter(|import_path| Path::new(import_path).exists());
+
+        let store = match import_path {
+            Some(import_path) => {
+                info!("Importing {} database into SQLite", import_path);
+                let json_data =
+                    fs::read_to_string(import_path).map_err(|e| StorageError::Io(e.to_string()))?;
+                let store = parse_json_store(&json_data, &self.migration_context)?;
+                self.save(&store)?;
+                Some(store)
+            }
+            None => None,
+        };
+
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                params![INITIALIZED_KEY, import_path.unwrap_or_default()],
+            )
+            .map_err(to_storage_error)?;
+
+        Ok(store)
+    }
+
+    fn is_initialized(&self) -> Result<bool, StorageError> {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT 1 FROM meta WHERE key = ?1",
+                params![INITIALIZED_KEY],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|row| row.is_some())
+            .map_err(to_storage_error)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> Result<Option<PlayerStore>, StorageError> {
+        if !self.is_initialized()? {
+            return self.import_json();
+        }
+
+        let connection = self.connection.lock().unwrap();
+
+        // SQLite databases appeared with version 2, before it was stored in the meta table
+        let schema_version: u32 = connection
+            .query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                params![SCHEMA_VERSION_KEY],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(to_storage_error)?
+            .and_then(|version| version.parse().ok())
+            .unwrap_or(2);
+
+        if schema_version < 4 {
+            info!("Moving stats snapshots to their u.gg profile");
+            connection
+                .execute_batch(MIGRATE_SNAPSHOTS)
+                .map_err(to_storage_error)?;
+        }
+        if schema_version < 5 {
+            canonicalize_snapshots(&connection)?;
+        }
+
+        // Rows go through the same migrations as the JSON database
+        let mut store = parse_store(
+            json!({
+                "schema_version": schema_version,
+                "registered_players": load_rows(&connection, "registered_players")?,
+                "players": load_rows(&connection, "players")?,
+                "guild_settings": load_rows(&connection, "guild_settings")?,
+                "user_settings": load_rows(&connection, "user_settings")?,
+            }),
+            &self.migration_context,
+        )?;
+
+        let mut statement = connection
+            .prepare(
+                "SELECT rematch_url, taken_at, current_league, current_division, matches_played, wins
+                 FROM profile_snapshots ORDER BY rematch_url, taken_at",
+            )
+            .map_err(to_storage_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                let rematch_url: String = row.get(0)?;
+                let taken_at: String = row.get(1)?;
+                let current_league: Option<i32> = row.get(2)?;
+                let current_division: Option<i32> = row.get(3)?;
+                let rank = match (current_league, current_division) {
+                    (Some(current_league), Some(current_division)) => Some(UggRank {
+                        current_league: League::from(current_league),
+                        current_division: Division::from(current_division),
+                    }),
+                    _ => None,
+                };
+
+                Ok((
+                    rematch_url,
+                    taken_at,
+                    rank,
+                    row.get::<_, i32>(4)?,
+                    row.get::<_, i32>(5)?,
+                ))
+            })
+            .map_err(to_storage_error)?;
+
+        for row in rows {
+            let (rematch_url, taken_at, rank, matches_played, wins) =
+                row.map_err(to_storage_error)?;
+            let taken_at = DateTime::parse_from_rfc3339(&taken_at)
+                .map_err(|e| StorageError::Parse(e.to_string()))?
+                .with_timezone(&Utc);
+
+            store.history.record(
+                &rematch_url,
+                StatsSnapshot {
+                    taken_at,
+                    rank,
+                    matches_played,
+                    wins,
+                },
+            );
+        }
+
+        Ok(Some(store))
+    }
+
+    fn save(&self, store: &PlayerStore) -> Result<(), StorageError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(to_storage_error)?;
+
+        save_rows(
+            &transaction,
+            "registered_players",
+            &store.registered_players,
+        )?;
+        save_rows(&transaction, "players", &store.players)?;
+        save_rows(&transaction, "guild_settings", &store.guild_settings)?;
+        save_rows(&transaction, "user_settings", &store.user_settings)?;
+        transaction
+            .execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                params![SCHEMA_VERSION_KEY, SCHEMA_VERSION.to_string()],
+            )
+            .map_err(to_storage_error)?;
+
+        // Snapshots pruned from memory are pruned from the database as well
+        transaction
+            .execute(
+                "CREATE TEMP TABLE IF NOT EXISTS kept (rematch_url TEXT, oldest TEXT)",
+                [],
+            )
+            .map_err(to_storage_error)?;
+        transaction
+            .execute("DELETE FROM kept", [])
+            .map_err(to_storage_error)?;
+
+        for (rematch_url, snapshots) in store.history.iter() {
+            let last_saved: Option<String> = transaction
+                .query_row(
+                    "SELECT MAX(taken_at) FROM profile_snapshots WHERE rematch_url = ?1",
+                    params![rematch_url],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(to_storage_error)?
+                .flatten();
+
+            for snapshot in snapshots {
+                let taken_at = format_taken_at(&snapshot.taken_at);
+                if last_saved.as_ref().is_some_and(|last| &taken_at <= last) {
+                    continue;
+                }
+
+                let rank = snapshot.rank.as_ref();
+                transaction
+                    .execute(
+                        "INSERT OR IGNORE INTO profile_snapshots
+                         (rematch_url, taken_at, current_league, current_division, matches_played, wins)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![
+                            rematch_url,
+                            taken_at,
+                            rank.map(|r| i32::from(r.current_league)),
+                            rank.map(|r| i32::from(r.current_division)),
+                            snapshot.matches_played,
+                            snapshot.wins
+                        ],
+                    )
+                    .map_err(to_storage_error)?;
+            }
+
+            if let Some(oldest) = snapshots.first() {
+                transaction
+                    .execute(
+                        "INSERT INTO kept (rematch_url, oldest) VALUES (?1, ?2)",
+                        params![rematch_url, format_taken_at(&oldest.taken_at)],
+                    )
+                    .map_err(to_storage_error)?;
+            }
+        }
+
+        transaction
+            .execute(
+                "DELETE FROM profile_snapshots WHERE NOT EXISTS (
+                    SELECT 1 FROM kept
+                    WHERE kept.rematch_url = profile_snapshots.rematch_url
+                        AND kept.oldest <= profile_snapshots.taken_at
+                )",
+                [],
+            )
+            .map_err(to_storage_error)?;
+
+        transaction.commit().map_err(to_storage_error)
+    }
+}
+
+/// Snapshots follow their profile URL to its canonical spelling, see the version 5 migration.
+fn canonicalize_snapshots(connection: &Connection) -> Result<(), StorageError> {
+    let rematch_urls: Vec<String> = connection
+        .prepare("SELECT DISTINCT rematch_url FROM profile_snapshots")
+        .and_then(|mut statement| {
+            statement
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()
+        })
+        .map_err(to_storage_error)?;
+
+    for rematch_url in rematch_urls {
+        let canonical = canonical_url(&rematch_url);
+        if canonical == rematch_url {
+            continue;
+        }
+
+        connection
+            .execute(
+                "UPDATE OR IGNORE profile_snapshots SET rematch_url = ?1 WHERE rematch_url = ?2",
+                params![canonical, rematch_url],
+            )
+            .and_then(|_| {
+                connection.execute(
+                    "DELETE FROM profile_snapshots WHERE rematch_url = ?1",
+                    params![rematch_url],
+                )
+            })
+            .map_err(to_storage_error)?;
+    }
+
+    Ok(())
+}
+
+/// Fixed width UTC timestamps, so they sort the same as text and as dates
+fn format_taken_at(taken_at: &DateTime<Utc>) -> String {
+    taken_at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn load_rows(connection: &Connection, table: &str) -> Result<Vec<Value>, StorageError> {
+    let mut statement = connection
+        .prepare(&format!("SELECT data FROM {} ORDER BY position", table))
+        .map_err(to_storage_error)?;
+
+    let rows = statement
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(to_storage_error)?;
+
+    let mut values = vec![];
+    for row in rows {
+        let data = row.map_err(to_storage_error)?;
+        let value = serde_json::from_str(&data).map_err(|e| {
+            error!("Could not parse a row of {}, {}", table, e);
+            StorageError::Parse(e.to_string())
+        })?;
+        values.push(value);
+    }
+
+    Ok(values)
+}
+
+fn save_rows<T: Serialize>(
+    connection: &Connection,
+    table: &str,
+    values: &[T],
+) -> Result<(), StorageError> {
+    connection
+        .execute(&format!("DELETE FROM {}", table), [])
+        .map_err(to_storage_error)?;
+
+    let mut statement = connection
+        .prepare(&format!(
+            "INSERT INTO {} (position, data) VALUES (?1, ?2)",
+            table
+        ))
+        .map_err(to_storage_error)?;
+
+    for (position, value) in values.iter().enumerate() {
+        let data = serde_json::to_string(value).map_err(|e| StorageError::Parse(e.to_string()))?;
+        statement
+            .execute(params![position as i64, data])
+            .map_err(to_storage_error)?;
+    }
+
+    Ok(())
+}
+
+fn to_storage_error(e: rusqlite::Error) -> StorageError {
+    StorageError::Sqlite(e.to_string())
+}
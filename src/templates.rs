@@ -0,0 +1,101 @@
+use std::{collections::HashMap, fs, path::PathBuf, sync::RwLock, time::SystemTime};
+
+use log::{error, info};
+use rand::seq::IndexedRandom;
+
+use crate::locale::{Locale, MessageArg, fill};
+
+/// Variants of the messages, by locale then by catalog key.
+type Variants = HashMap<Locale, HashMap<String, Vec<String>>>;
+
+#[derive(Debug, Default)]
+struct LoadedTemplates {
+    modified: Option<SystemTime>,
+    variants: Variants,
+}
+
+/// Replies tuned by the guild without a rebuild, read from a JSON file such as
+/// `{"fr": {"stats.try_harder": ["{player} a encore joué {hours}h !", "..."]}}`.
+/// The file is read again once it changed, a message it does not have comes from the catalog.
+#[derive(Debug)]
+pub struct Templates {
+    path: PathBuf,
+    loaded: RwLock<LoadedTemplates>,
+}
+
+/// The messages of a reply, in its locale.
+#[derive(Debug, Clone, Copy)]
+pub struct Messages<'a> {
+    pub locale: Locale,
+    templates: &'a Templates,
+}
+
+impl Templates {
+    pub fn new(path: &str) -> Self {
+        let templates = Templates {
+            path: PathBuf::from(path),
+            loaded: RwLock::new(LoadedTemplates::default()),
+        };
+        templates.reload_if_changed();
+
+        templates
+    }
+
+    /// Also picks up the latest edits of the file.
+    pub fn messages(&self, locale: Locale) -> Messages<'_> {
+        self.reload_if_changed();
+        Messages {
+            locale,
+            templates: self,
+        }
+    }
+
+    /// A file that does not parse leaves the previous templates in place.
+    fn reload_if_changed(&self) {
+        let modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified == self.loaded.read().unwrap().modified {
+            return;
+        }
+
+        let mut loaded = self.loaded.write().unwrap();
+        loaded.modified = modified;
+        if modified.is_none() {
+            info!("No templates at {}", self.path.display());
+            loaded.variants = Variants::default();
+            return;
+        }
+
+        let variants = fs::read_to_string(&self.path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()));
+        match variants {
+            Ok(variants) => {
+                info!("Templates loaded from {}", self.path.display());
+                loaded.variants = variants;
+            }
+            Err(e) => error!(
+                "Could not read templates {}, keeping the previous ones, {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+
+    fn pick_variant(&self, locale: Locale, key: &str) -> Option<String> {
+        let loaded = self.loaded.read().unwrap();
+        let variants = loaded.variants.get(&locale)?.get(key)?;
+        variants.choose(&mut rand::rng()).cloned()
+    }
+}
+
+impl Messages<'_> {
+    /// One of the template variants at random, the catalog message when there are none.
+    pub fn get(&self, key: &str, args: &[MessageArg]) -> String {
+        match self.templates.pick_variant(self.locale, key) {
+            Some(template) => fill(&template, args),
+            None => self.locale.message(key, args),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::{thread::sleep, time::Duration};
+
+use rebot::circuit_breaker::{CircuitBreaker, CircuitState};
+
+const COOLDOWN: Duration = Duration::from_millis(50);
+
+fn open_breaker() -> CircuitBreaker {
+    let breaker = CircuitBreaker::new(3, COOLDOWN);
+    for _ in 0..3 {
+        assert!(breaker.allow_request());
+        breaker.record_failure();
+    }
+
+    breaker
+}
+
+#[test]
+fn opens_after_consecutive_failures() {
+    let breaker = CircuitBreaker::new(3, COOLDOWN);
+    breaker.record_failure();
+    breaker.record_failure();
+    breaker.record_success();
+    breaker.record_failure();
+    breaker.record_failure();
+    assert_eq!(breaker.status().state, CircuitState::Closed);
+    assert!(breaker.allow_request());
+
+    breaker.record_failure();
+    let status = breaker.status();
+    assert!(status.is_open());
+    assert_eq!(status.consecutive_failures, 3);
+    assert!(status.retry_in_second.is_some());
+    assert!(!breaker.allow_request());
+}
+
+#[test]
+fn half_open_lets_a_single_probe_through() {
+    let breaker = open_breaker();
+    sleep(COOLDOWN);
+
+    assert_eq!(breaker.status().state, CircuitState::HalfOpen);
+    assert!(breaker.allow_request());
+    assert!(!breaker.allow_request());
+    assert!(!breaker.allow_request());
+}
+
+#[test]
+fn probe_result_closes_or_opens_again() {
+    let breaker = open_breaker();
+    sleep(COOLDOWN);
+    assert!(breaker.allow_request());
+    breaker.record_failure();
+    assert!(breaker.status().is_open());
+    assert!(!breaker.allow_request());
+
+    sleep(COOLDOWN);
+    assert!(breaker.allow_request());
+    breaker.record_success();
+    let status = breaker.status();
+    assert_eq!(status.state, CircuitState::Closed);
+    assert_eq!(status.consecutive_failures, 0);
+    assert!(breaker.allow_request());
+    assert!(breaker.allow_request());
+}
+
+#[test]
+fn lost_probe_is_replaced_after_a_cooldown() {
+    let breaker = open_breaker();
+    sleep(COOLDOWN);
+    assert!(breaker.allow_request());
+    assert!(!breaker.allow_request());
+
+    sleep(COOLDOWN);
+    assert!(breaker.allow_request());
+}
@@ -0,0 +1,37 @@
+#![allow(dead_code)]
+
+use std::{env, fs, path::PathBuf};
+
+use rebot::{
+    player_store::PlayerStore,
+    storage::{json::parse_json_store, migration::MigrationContext},
+    templates::Templates,
+};
+
+pub const DB_V4: &str = include_str!("../fixtures/db_v4.json");
+
+/// Owns every account of the version 4 sample
+pub const DISCORD_ID: u64 = 123456789012345678;
+/// Shows the accounts of a user added up, the steam one being the main
+pub const MAIN_GUILD_ID: u64 = 876543210987654321;
+/// Lists each account on its own line, the xbox one being the main
+pub const LISTING_GUILD_ID: u64 = 765432109876543210;
+
+/// The version 4 sample, without storage.
+pub fn load_db_v4() -> PlayerStore {
+    parse_json_store(DB_V4, &MigrationContext::default()).unwrap()
+}
+
+/// Messages straight from the catalog, without any template file.
+pub fn catalog() -> Templates {
+    Templates::new("")
+}
+
+/// An empty directory of its own for the test, left behind if the test fails.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rebot-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
@@ -0,0 +1,25 @@
+use std::env;
+
+use rebot::config::Config;
+
+// A single test, the environment is shared by the whole test binary
+#[test]
+fn default_guild_falls_back_to_the_discord_server() {
+    // SAFETY: no other test of this binary reads or writes the environment
+    unsafe {
+        env::set_var("DISCORD_TOKEN", "token");
+        env::set_var("DATABASE_PATH", "db.json");
+        env::set_var("DISCORD_SERVER_ID", "876543210987654321");
+        env::set_var("DEFAULT_GUILD_ID", "");
+    }
+    assert_eq!(Config::new().default_guild_id, Some(876543210987654321));
+
+    unsafe { env::set_var("DEFAULT_GUILD_ID", "  ") };
+    assert_eq!(Config::new().default_guild_id, Some(876543210987654321));
+
+    unsafe { env::remove_var("DEFAULT_GUILD_ID") };
+    assert_eq!(Config::new().default_guild_id, Some(876543210987654321));
+
+    unsafe { env::set_var("DEFAULT_GUILD_ID", "765432109876543210") };
+    assert_eq!(Config::new().default_guild_id, Some(765432109876543210));
+}
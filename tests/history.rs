@@ -0,0 +1,183 @@
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+mod common;
+
+use rebot::{
+    history::{GamesPlayed, StatsHistory, StatsSnapshot},
+    model::player_stat::UggRank,
+};
+use serde_json::json;
+
+use common::{DISCORD_ID, load_db_v4};
+
+const URL: &str = "https://u.gg/rematch/profile/steam/Zizou/76561198000000000";
+const STEAM_URL: &str =
+    "https://u.gg/rematch/profile/steam/La%20m%C3%A9sange%20du%20Val%20d'Oise/76561198355389674";
+const XBOX_URL: &str = "https://u.gg/rematch/profile/xbox/Petite%20m%C3%A9sange/2535412345678901";
+
+fn day(day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 9, day, 12, 0, 0).unwrap()
+}
+
+fn snapshot(
+    day_of_month: u32,
+    current_league: i32,
+    matches_played: i32,
+    wins: i32,
+) -> StatsSnapshot {
+    let rank: UggRank = serde_json::from_value(json!({
+        "current_league": current_league,
+        "current_division": 0,
+    }))
+    .unwrap();
+
+    StatsSnapshot {
+        taken_at: day(day_of_month),
+        rank: Some(rank),
+        matches_played,
+        wins,
+    }
+}
+
+fn history() -> StatsHistory {
+    let mut history = StatsHistory::default();
+    for s in [
+        snapshot(1, 2, 10, 5),
+        snapshot(8, 3, 30, 18),
+        snapshot(15, 3, 45, 25),
+        snapshot(22, 4, 70, 40),
+    ] {
+        history.record(URL, s);
+    }
+
+    history
+}
+
+#[test]
+fn unchanged_stats_are_not_recorded() {
+    let mut history = history();
+
+    assert!(!history.record_change(URL, snapshot(23, 4, 70, 40)));
+    assert!(history.record_change(URL, snapshot(24, 4, 71, 40)));
+    assert!(history.record_change(URL, snapshot(25, 5, 71, 40)));
+    assert_eq!(history.get_snapshots(URL).len(), 6);
+    assert_eq!(history.get_latest(URL).unwrap().taken_at, day(25));
+}
+
+#[test]
+fn prune_keeps_the_latest_snapshot() {
+    let mut history = history();
+
+    history.prune(None, day(30));
+    assert_eq!(history.get_snapshots(URL).len(), 4);
+
+    history.prune(Some(TimeDelta::days(10)), day(30));
+    let kept: Vec<_> = history
+        .get_snapshots(URL)
+        .iter()
+        .map(|s| s.taken_at)
+        .collect();
+    assert_eq!(kept, vec![day(22)]);
+
+    // Long idle, the current stats are still known
+    history.prune(Some(TimeDelta::days(1)), day(30));
+    assert_eq!(history.get_latest(URL).unwrap().taken_at, day(22));
+}
+
+#[test]
+fn games_played_between_two_dates() {
+    let history = history();
+
+    // From the stats known on the 5th, those of the 1st, to those of the 15th
+    let delta = history.stats_between(URL, day(5), day(20)).unwrap();
+    assert_eq!(delta.from.taken_at, day(1));
+    assert_eq!(delta.to.taken_at, day(15));
+    assert_eq!(delta.matches_played, 35);
+    assert_eq!(delta.wins, 20);
+
+    // Games played before the only snapshot of the window count as well
+    let delta = history.stats_between(URL, day(18), day(25)).unwrap();
+    assert_eq!(delta.from.taken_at, day(15));
+    assert_eq!(delta.to.taken_at, day(22));
+    assert_eq!(delta.matches_played, 25);
+    assert_eq!(delta.wins, 15);
+
+    // Idle window
+    let delta = history.stats_between(URL, day(16), day(20)).unwrap();
+    assert_eq!(delta.from.taken_at, day(15));
+    assert_eq!(delta.to.taken_at, day(15));
+    assert_eq!(delta.matches_played, 0);
+    assert_eq!(delta.wins, 0);
+
+    // Counted from the first snapshot when the window opens before it
+    let delta = history
+        .stats_between(URL, day(1) - TimeDelta::days(3), day(8))
+        .unwrap();
+    assert_eq!(delta.from.taken_at, day(1));
+    assert_eq!(delta.matches_played, 20);
+
+    assert!(
+        history
+            .stats_between(
+                URL,
+                day(1) - TimeDelta::days(3),
+                day(1) - TimeDelta::days(1)
+            )
+            .is_none()
+    );
+    assert!(history.stats_between("unknown", day(1), day(30)).is_none());
+}
+
+#[test]
+fn games_played_by_a_user_add_up_their_accounts() {
+    let at = |day: u32| Utc.with_ymd_and_hms(2025, 10, day, 20, 0, 0).unwrap();
+    let mut store = load_db_v4();
+    store.history.record(
+        STEAM_URL,
+        StatsSnapshot {
+            taken_at: at(15),
+            rank: None,
+            matches_played: 260,
+            wins: 147,
+        },
+    );
+    for (day, matches_played, wins) in [(10, 40, 25), (14, 50, 30)] {
+        store.history.record(
+            XBOX_URL,
+            StatsSnapshot {
+                taken_at: at(day),
+                rank: None,
+                matches_played,
+                wins,
+            },
+        );
+    }
+
+    // Both accounts are registered in two guilds, they are counted once
+    let games = store.get_games_between(DISCORD_ID, at(11), at(16)).unwrap();
+    assert_eq!(
+        games,
+        GamesPlayed {
+            matches_played: 20,
+            wins: 12,
+        }
+    );
+    assert!(store.get_games_between(42, at(11), at(16)).is_none());
+}
+
+#[test]
+fn first_snapshot_reaching_a_league() {
+    let history = history();
+    let in_league = |league: i32| {
+        move |s: &StatsSnapshot| {
+            s.rank
+                .as_ref()
+                .is_some_and(|rank| i32::from(rank.current_league) >= league)
+        }
+    };
+
+    assert_eq!(
+        history.find_first(URL, in_league(3)).unwrap().taken_at,
+        day(8)
+    );
+    assert!(history.find_first(URL, in_league(5)).is_none());
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use std::{fs, path::Path, thread::sleep, time::Duration};
+
+use rebot::{
+    config::{Config, DatabaseBackend},
+    player_store::PlayerStore,
+    storage::{Storage, StorageError, json::JsonStorage, migration::MigrationContext},
+};
+
+use common::{load_db_v4, temp_dir};
+
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+
+    names
+}
+
+fn storage(dir: &Path, backup_count: usize) -> JsonStorage {
+    JsonStorage::new(
+        dir.join("db.json").to_str().unwrap(),
+        backup_count,
+        MigrationContext::default(),
+    )
+}
+
+#[test]
+fn saves_replace_the_database_whole() {
+    let dir = temp_dir("json-save");
+    let storage = storage(&dir, 0);
+    assert!(storage.load().unwrap().is_none());
+
+    let mut store = load_db_v4();
+    storage.save(&store).unwrap();
+    store.registered_players.truncate(1);
+    storage.save(&store).unwrap();
+
+    // No temporary file left behind, nor backups when none are asked for
+    assert_eq!(file_names(&dir), ["db.json"]);
+    let loaded = storage.load().unwrap().unwrap();
+    assert_eq!(loaded.registered_players.len(), 1);
+    assert_eq!(loaded.players.len(), 2);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn backups_are_rotated() {
+    let dir = temp_dir("json-rotate");
+    let storage = storage(&dir, 2);
+
+    let mut store = load_db_v4();
+    for registered_count in (1..=4).rev() {
+        store.registered_players.truncate(registered_count);
+        storage.save(&store).unwrap();
+        // Backup names are precise to the millisecond
+        sleep(Duration::from_millis(5));
+    }
+
+    let names = file_names(&dir);
+    let backups: Vec<&String> = names.iter().filter(|name| name.ends_with(".bak")).collect();
+    assert_eq!(backups.len(), 2);
+
+    // The newest backup is the previous save
+    let newest_backup = fs::read_to_string(dir.join(backups[1])).unwrap();
+    let newest_backup: serde_json::Value = serde_json::from_str(&newest_backup).unwrap();
+    assert_eq!(
+        newest_backup["registered_players"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn corrupt_database_is_quarantined_for_the_newest_backup() {
+    let dir = temp_dir("json-corrupt");
+    let storage = storage(&dir, 3);
+
+    let mut store = load_db_v4();
+    storage.save(&store).unwrap();
+    sleep(Duration::from_millis(5));
+    store.registered_players.truncate(3);
+    storage.save(&store).unwrap();
+    fs::write(dir.join("db.json"), "{\"registered_players\": [").unwrap();
+
+    let loaded = storage.load().unwrap().unwrap();
+    assert_eq!(loaded.registered_players.len(), 4);
+
+    // Kept aside as is, the next save cannot overwrite it
+    let names = file_names(&dir);
+    let quarantined: Vec<&String> = names
+        .iter()
+        .filter(|name| name.starts_with("db.json.corrupt-"))
+        .collect();
+    assert_eq!(quarantined.len(), 1);
+    assert_eq!(
+        fs::read_to_string(dir.join(quarantined[0])).unwrap(),
+        "{\"registered_players\": ["
+    );
+    assert!(!dir.join("db.json").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn corrupt_database_without_backup_starts_empty() {
+    let dir = temp_dir("json-corrupt-alone");
+    let storage = storage(&dir, 3);
+    fs::write(dir.join("db.json"), "not json").unwrap();
+
+    assert!(storage.load().unwrap().is_none());
+    assert_eq!(file_names(&dir).len(), 1);
+    assert!(!dir.join("db.json").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn database_from_a_newer_bot_is_not_loaded() {
+    let dir = temp_dir("json-newer");
+    let path = dir.join("db.json");
+    let newer = "{\"schema_version\": 999, \"registered_players\": []}";
+    fs::write(&path, newer).unwrap();
+
+    let config = Config {
+        database_path: path.to_str().unwrap().to_string(),
+        database_backend: DatabaseBackend::Json,
+        ..Default::default()
+    };
+    assert!(matches!(
+        PlayerStore::load_database(&config),
+        Err(StorageError::Migration(_))
+    ));
+    assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+
+    fs::remove_dir_all(dir).unwrap();
+}
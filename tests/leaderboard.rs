@@ -0,0 +1,101 @@
+mod common;
+
+use rebot::player_store::{LeaderboardSort, PlayerStore, RegisteredPlayer};
+
+use common::{DISCORD_ID, LISTING_GUILD_ID, load_db_v4};
+
+const OTHER_DISCORD_ID: u64 = 234567890123456789;
+const GRANDE_MESANGE_URL: &str =
+    "https://u.gg/rematch/profile/epic/Grande%20m%C3%A9sange/0123456789abcdef";
+
+/// Adds a player with fewer matches but more wins than the main account of the sample, and the
+/// lowest level, so that each counter gives another order.
+fn store_with_third_player() -> PlayerStore {
+    let mut store = load_db_v4();
+    store.registered_players.push(RegisteredPlayer {
+        guild_id: LISTING_GUILD_ID,
+        discord_id: OTHER_DISCORD_ID,
+        rematch_url: String::from(GRANDE_MESANGE_URL),
+        label: None,
+        main: true,
+    });
+
+    let mut player = store
+        .get_player_stat(LISTING_GUILD_ID, DISCORD_ID)
+        .unwrap()
+        .clone();
+    player.discord_id = OTHER_DISCORD_ID;
+    player.rematch_url = String::from(GRANDE_MESANGE_URL);
+    player.display_name = String::from("Grande mésange");
+    player.level = 5;
+    player.stats.all.matches_played = 200;
+    player.stats.all.wins = 150;
+    store.players.push(player);
+
+    store
+}
+
+fn leaderboard_names(store: &PlayerStore, sort: LeaderboardSort) -> Vec<String> {
+    store
+        .get_leaderboard(LISTING_GUILD_ID, sort)
+        .into_iter()
+        .map(|p| p.display_name)
+        .collect()
+}
+
+fn sample_leaderboard_names(sort: LeaderboardSort, min_matches: i32) -> Vec<String> {
+    let mut store = load_db_v4();
+    store.config.leaderboard_min_matches = min_matches;
+
+    leaderboard_names(&store, sort)
+}
+
+#[test]
+fn sorted_by_rank_by_default() {
+    assert_eq!(
+        sample_leaderboard_names(LeaderboardSort::default(), 0),
+        ["La mésange du Val d'Oise", "Petite mésange"]
+    );
+}
+
+#[test]
+fn sorted_by_win_rate_with_minimum_games() {
+    assert_eq!(
+        sample_leaderboard_names(LeaderboardSort::WinRate, 0),
+        ["Petite mésange", "La mésange du Val d'Oise"]
+    );
+    assert_eq!(
+        sample_leaderboard_names(LeaderboardSort::WinRate, 100),
+        ["La mésange du Val d'Oise", "Petite mésange"]
+    );
+}
+
+#[test]
+fn sorted_by_counters() {
+    let store = store_with_third_player();
+
+    assert_eq!(
+        leaderboard_names(&store, LeaderboardSort::Matches),
+        [
+            "La mésange du Val d'Oise",
+            "Grande mésange",
+            "Petite mésange"
+        ]
+    );
+    assert_eq!(
+        leaderboard_names(&store, LeaderboardSort::Wins),
+        [
+            "Grande mésange",
+            "La mésange du Val d'Oise",
+            "Petite mésange"
+        ]
+    );
+    assert_eq!(
+        leaderboard_names(&store, LeaderboardSort::Level),
+        [
+            "La mésange du Val d'Oise",
+            "Petite mésange",
+            "Grande mésange"
+        ]
+    );
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use rebot::locale::{Locale, fill};
+
+use common::{DISCORD_ID, LISTING_GUILD_ID as GUILD_ID, load_db_v4};
+
+fn placeholders(template: &str) -> BTreeSet<&str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+        .collect()
+}
+
+#[test]
+fn catalogs_have_the_same_messages() {
+    let default_catalog = Locale::default().catalog();
+    for locale in Locale::ALL {
+        let catalog = locale.catalog();
+        assert_eq!(catalog.len(), default_catalog.len(), "{:?}", locale);
+
+        for (key, template) in default_catalog {
+            let (_, translation) = catalog
+                .iter()
+                .find(|(translation_key, _)| translation_key == key)
+                .unwrap_or_else(|| panic!("{} missing in {:?}", key, locale));
+            assert_eq!(
+                placeholders(translation),
+                placeholders(template),
+                "{} in {:?}",
+                key,
+                locale
+            );
+        }
+    }
+}
+
+#[test]
+fn placeholders_are_filled_once() {
+    assert_eq!(
+        fill(
+            "{user} vs {rank} {unknown}",
+            &[("user", &"{rank}"), ("rank", &"or 1")]
+        ),
+        "{rank} vs or 1 {unknown}"
+    );
+    assert_eq!(
+        Locale::En.message("refresh.progress", &[("done", &7), ("total", &15)]),
+        "Scraping: 7/15 players…"
+    );
+}
+
+#[test]
+fn user_locale_overrides_guild_locale() {
+    let mut store = load_db_v4();
+    assert_eq!(store.get_locale(Some(GUILD_ID), DISCORD_ID), Locale::Fr);
+
+    store.set_guild_locale(GUILD_ID, Locale::En);
+    assert_eq!(store.get_locale(Some(GUILD_ID), DISCORD_ID), Locale::En);
+    assert_eq!(store.get_locale(None, DISCORD_ID), Locale::Fr);
+
+    store.set_user_locale(DISCORD_ID, Some(Locale::Fr));
+    assert_eq!(store.get_locale(Some(GUILD_ID), DISCORD_ID), Locale::Fr);
+
+    store.forget_player(DISCORD_ID);
+    assert_eq!(store.get_locale(Some(GUILD_ID), DISCORD_ID), Locale::En);
+}
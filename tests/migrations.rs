@@ -0,0 +1,190 @@
+mod common;
+
+use rebot::{
+    locale::Locale,
+    storage::{
+        StorageError,
+        json::parse_json_store,
+        migration::{MigrationContext, SCHEMA_VERSION, get_schema_version, migrate},
+    },
+};
+use serde_json::{Value, json};
+
+use common::catalog;
+
+const DB_V1: &str = include_str!("fixtures/db_v1.json");
+const DB_V2: &str = include_str!("fixtures/db_v2.json");
+const DB_V3: &str = include_str!("fixtures/db_v3.json");
+
+const DB_V4: &str = include_str!("fixtures/db_v4.json");
+
+const DEFAULT_GUILD_ID: u64 = 876543210987654321;
+const MESANGE_URL: &str =
+    "https://u.gg/rematch/profile/steam/La%20m%C3%A9sange%20du%20Val%20d'Oise/76561198355389674";
+
+fn context() -> MigrationContext {
+    MigrationContext {
+        default_guild_id: Some(DEFAULT_GUILD_ID),
+    }
+}
+
+fn to_value(json_data: &str) -> Value {
+    serde_json::from_str(json_data).unwrap()
+}
+
+#[test]
+fn unversioned_file_is_version_1() {
+    assert_eq!(get_schema_version(&to_value(DB_V1)).unwrap(), 1);
+}
+
+#[test]
+fn every_sample_migrates_to_the_current_version() {
+    for sample in [DB_V1, DB_V2, DB_V3, DB_V4] {
+        let migrated = migrate(to_value(sample), &context()).unwrap();
+        assert_eq!(get_schema_version(&migrated).unwrap(), SCHEMA_VERSION);
+    }
+}
+
+#[test]
+fn loads_v1_sample() {
+    let store = parse_json_store(DB_V1, &context()).unwrap();
+
+    assert_eq!(store.registered_players.len(), 2);
+    assert_eq!(store.players.len(), 2);
+    assert_eq!(store.players[0].get_all_matches(), 250);
+    assert_eq!(store.players[0].get_wins(), 140);
+    assert_eq!(
+        store.players[0].pretty_rank(catalog().messages(Locale::Fr)),
+        "diamant 2"
+    );
+    assert_eq!(
+        store.players[1].pretty_rank(catalog().messages(Locale::Fr)),
+        "non classé"
+    );
+    assert!(!store.players[0].stale);
+    assert!(store.history.get_snapshots(MESANGE_URL).is_empty());
+    assert!(store.guild_settings.is_empty());
+    assert!(
+        store
+            .registered_players
+            .iter()
+            .all(|p| p.guild_id == DEFAULT_GUILD_ID)
+    );
+    assert_eq!(
+        store.players[1].rematch_url,
+        store.registered_players[1].rematch_url
+    );
+}
+
+#[test]
+fn loads_v2_sample() {
+    let store = parse_json_store(DB_V2, &context()).unwrap();
+
+    assert_eq!(store.registered_players.len(), 1);
+    assert_eq!(store.players[0].level, 42);
+    assert_eq!(
+        store.players[0]
+            .get_pretty_mode_stats("Ranked", catalog().messages(Locale::En))
+            .unwrap(),
+        "101W 79L, 56.1% Win Rate"
+    );
+    assert_eq!(store.history.get_snapshots(MESANGE_URL).len(), 2);
+    assert_eq!(
+        store.guild_settings[0].announcement_channel_id,
+        Some(987654321098765432)
+    );
+    assert_eq!(store.registered_players[0].guild_id, DEFAULT_GUILD_ID);
+    assert!(
+        store
+            .get_player_stat(DEFAULT_GUILD_ID, 123456789012345678)
+            .is_some()
+    );
+}
+
+#[test]
+fn loads_v3_sample() {
+    let store = parse_json_store(DB_V3, &MigrationContext::default()).unwrap();
+
+    assert_eq!(store.registered_players.len(), 2);
+    assert_eq!(store.players.len(), 1);
+    for guild_id in [DEFAULT_GUILD_ID, 765432109876543210] {
+        assert_eq!(store.get_all_players_stat(guild_id).len(), 1);
+    }
+    assert!(store.get_all_players_stat(1).is_empty());
+    assert!(store.registered_players.iter().all(|p| p.main));
+    assert_eq!(store.history.get_snapshots(MESANGE_URL).len(), 1);
+}
+
+#[test]
+fn loads_v4_sample() {
+    let store = parse_json_store(DB_V4, &MigrationContext::default()).unwrap();
+
+    assert_eq!(store.registered_players.len(), 4);
+    assert_eq!(store.players.len(), 2);
+    let accounts = store.get_player_accounts(DEFAULT_GUILD_ID, 123456789012345678);
+    assert_eq!(accounts.len(), 2);
+    assert!(accounts[0].main);
+    assert_eq!(accounts[1].label.as_deref(), Some("smurf"));
+
+    let main = store
+        .get_player_stat(DEFAULT_GUILD_ID, 123456789012345678)
+        .unwrap();
+    assert_eq!(main.display_name, "La mésange du Val d'Oise");
+    let smurf = store
+        .get_account_stat(DEFAULT_GUILD_ID, 123456789012345678, "Smurf")
+        .unwrap();
+    assert_eq!(smurf.display_name, "Petite mésange");
+
+    // Aggregated by default, listed in the guild asking for it
+    let aggregated = store.get_all_players_stat(DEFAULT_GUILD_ID);
+    assert_eq!(aggregated.len(), 1);
+    assert_eq!(aggregated[0].display_name, "La mésange du Val d'Oise");
+    assert_eq!(aggregated[0].get_all_matches(), 300);
+    assert_eq!(aggregated[0].get_wins(), 170);
+    assert_eq!(store.get_all_players_stat(765432109876543210).len(), 2);
+}
+
+#[test]
+fn canonicalizes_profile_urls() {
+    let spelled_differently = "https://www.u.gg/rematch/profile/steam/La%20m%c3%a9sange%20du%20Val%20d%27Oise/76561198355389674?tab=overview";
+    let v4 = json!({
+        "schema_version": 4,
+        "registered_players": [
+            { "guild_id": DEFAULT_GUILD_ID, "discord_id": 1, "rematch_url": MESANGE_URL, "main": true },
+            { "guild_id": DEFAULT_GUILD_ID, "discord_id": 1, "rematch_url": spelled_differently, "main": false },
+        ],
+        "players": [],
+        "history": { "snapshots": {
+            spelled_differently: [{ "taken_at": "2025-10-12T20:00:00Z", "rank": null, "matches_played": 2, "wins": 1 }],
+            MESANGE_URL: [{ "taken_at": "2025-10-11T20:00:00Z", "rank": null, "matches_played": 1, "wins": 1 }],
+        } },
+    });
+
+    let store = parse_json_store(&v4.to_string(), &context()).unwrap();
+
+    assert_eq!(store.registered_players.len(), 1);
+    let snapshots = store.history.get_snapshots(MESANGE_URL);
+    assert_eq!(snapshots.len(), 2);
+    assert!(snapshots[0].taken_at < snapshots[1].taken_at);
+}
+
+#[test]
+fn needs_a_default_guild_to_assign_players() {
+    let result = migrate(to_value(DB_V2), &MigrationContext::default());
+
+    assert!(matches!(result, Err(StorageError::Migration(_))));
+}
+
+#[test]
+fn refuses_a_newer_version() {
+    let newer = json!({
+        "schema_version": SCHEMA_VERSION + 1,
+        "registered_players": [],
+        "players": [],
+    });
+
+    assert!(matches!(
+        migrate(newer, &context()),
+        Err(StorageError::Migration(_))
+    ));
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use std::{fs, time::Duration};
+
+use rebot::{
+    player_store::RegisteredPlayer,
+    scraper::ScrapeError,
+    stats_source::{
+        StatsSource, archive::PayloadArchive, fixture::FixtureSource, parse_player_stat,
+    },
+};
+
+use common::{DISCORD_ID, LISTING_GUILD_ID, MAIN_GUILD_ID, load_db_v4, temp_dir};
+
+const STEAM_URL: &str =
+    "https://u.gg/rematch/profile/steam/La%20m%C3%A9sange%20du%20Val%20d'Oise/76561198355389674";
+const XBOX_URL: &str = "https://u.gg/rematch/profile/xbox/Petite%20m%C3%A9sange/2535412345678901";
+const PAYLOAD: &str = include_str!("fixtures/ugg/steam-76561198355389674.json");
+
+fn player(rematch_url: &str) -> RegisteredPlayer {
+    RegisteredPlayer {
+        guild_id: MAIN_GUILD_ID,
+        discord_id: DISCORD_ID,
+        rematch_url: String::from(rematch_url),
+        label: None,
+        main: true,
+    }
+}
+
+#[test]
+fn schema_changes_are_parse_errors() {
+    assert!(parse_player_stat(PAYLOAD).is_ok());
+
+    let renamed = PAYLOAD.replace("lifetime_stats", "career_stats");
+    let error = parse_player_stat(&renamed).unwrap_err();
+    assert!(matches!(error, ScrapeError::Parse(_)));
+    assert!(!error.is_transient());
+
+    assert!(matches!(
+        parse_player_stat("<html>Too many requests</html>"),
+        Err(ScrapeError::Parse(_))
+    ));
+}
+
+#[tokio::test]
+async fn broken_fixture_fails_the_player_only() {
+    let dir = temp_dir("broken-fixture");
+    fs::write(dir.join("steam-76561198355389674.json"), "{\"player\": {}}").unwrap();
+    fs::write(dir.join("xbox-2535412345678901.json"), PAYLOAD).unwrap();
+    let source = FixtureSource::new(dir.to_str().unwrap());
+
+    let fetches = source
+        .get_players_stats(&[player(STEAM_URL), player(XBOX_URL)], &|_| {})
+        .await;
+    assert!(matches!(fetches[0].result, Err(ScrapeError::Parse(_))));
+    assert!(fetches[1].result.is_ok());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn payloads_are_archived_per_account() {
+    let dir = temp_dir("archive");
+    let archive = PayloadArchive::new(dir.to_str());
+    archive.save(STEAM_URL, PAYLOAD).await;
+    // File names are precise to the millisecond
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    archive.save(STEAM_URL, "not even json").await;
+    archive.save(XBOX_URL, PAYLOAD).await;
+
+    let archived = |prefix: &str| {
+        fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with(prefix)
+            })
+            .count()
+    };
+    assert_eq!(archived("steam-76561198355389674-"), 2);
+    assert_eq!(archived("xbox-2535412345678901-"), 1);
+
+    archive.remove(&[String::from(STEAM_URL)]).await;
+    assert_eq!(archived("steam-"), 0);
+    assert_eq!(archived("xbox-2535412345678901-"), 1);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn payloads_of_unregistered_profiles_are_removed() {
+    let dir = temp_dir("unregister");
+    let archive = PayloadArchive::new(dir.to_str());
+    archive.save(STEAM_URL, PAYLOAD).await;
+    let archived = || fs::read_dir(&dir).unwrap().count();
+
+    // Still registered in the other guild
+    let mut store = load_db_v4();
+    let removed =
+        store.unregister_player(MAIN_GUILD_ID, DISCORD_ID, Some("La mésange du Val d'Oise"));
+    assert_eq!(removed.count, 1);
+    assert!(removed.forgotten_profiles.is_empty());
+    archive.remove(&removed.forgotten_profiles).await;
+    assert_eq!(archived(), 1);
+
+    let removed = store.unregister_player(LISTING_GUILD_ID, DISCORD_ID, Some("steam"));
+    assert_eq!(removed.count, 1);
+    assert_eq!(removed.forgotten_profiles, vec![String::from(STEAM_URL)]);
+    archive.remove(&removed.forgotten_profiles).await;
+    assert_eq!(archived(), 0);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use rebot::{
+    locale::Locale,
+    model::player_stat::{UggLifetimeStats, UggPlayerStat},
+    player_store::{PlayerWithStats, RegisteredPlayer},
+    stats_source::parse_player_stat,
+};
+use serde_json::json;
+
+use common::catalog;
+
+const PAYLOAD: &str = include_str!("fixtures/ugg/steam-76561198355389674.json");
+
+#[test]
+fn every_game_mode_is_read() {
+    let player_stat: UggPlayerStat = parse_player_stat(PAYLOAD).unwrap();
+    let stats = &player_stat.lifetime_stats;
+
+    assert_eq!(stats.all.matches_played, 260);
+    let ranked = stats.get_mode("Ranked").unwrap();
+    assert_eq!((ranked.matches_played, ranked.wins), (180, 101));
+    assert_eq!(
+        (ranked.goals, ranked.assists, ranked.saves, ranked.mvps),
+        (290, 140, 61, 40)
+    );
+    assert_eq!(stats.get_mode("QuickMatch").unwrap().wins, 46);
+    assert!(stats.get_mode("Tournament").is_none());
+}
+
+#[test]
+fn unknown_modes_and_counters_are_kept() {
+    let stats: UggLifetimeStats = serde_json::from_value(json!({
+        "All": {"matches_played": 12, "wins": 7, "shots": 40},
+        "Tournament": {"matches_played": 2, "wins": 1},
+    }))
+    .unwrap();
+
+    // Counters missing from older payloads are zero
+    let tournament = stats.get_mode("Tournament").unwrap();
+    assert_eq!((tournament.goals, tournament.mvps), (0, 0));
+
+    let saved = serde_json::to_value(&stats).unwrap();
+    assert_eq!(saved["All"]["shots"], 40);
+    assert_eq!(saved["Tournament"]["wins"], 1);
+}
+
+#[test]
+fn modes_are_added_up_across_accounts() {
+    let mut stats = parse_player_stat(PAYLOAD).unwrap().lifetime_stats;
+    let other: UggLifetimeStats = serde_json::from_value(json!({
+        "All": {"matches_played": 10, "wins": 4, "goals": 3},
+        "Ranked": {"matches_played": 10, "wins": 4, "goals": 3},
+        "Tournament": {"matches_played": 2, "wins": 1},
+    }))
+    .unwrap();
+    stats.add(&other);
+
+    assert_eq!(stats.all.matches_played, 270);
+    assert_eq!(stats.get_mode("Ranked").unwrap().goals, 293);
+    assert_eq!(stats.get_mode("QuickMatch").unwrap().matches_played, 80);
+    assert_eq!(stats.get_mode("Tournament").unwrap().wins, 1);
+}
+
+#[test]
+fn counters_are_shown_per_mode() {
+    let registered_player = RegisteredPlayer {
+        guild_id: 876543210987654321,
+        discord_id: 123456789012345678,
+        rematch_url: String::from(
+            "https://u.gg/rematch/profile/steam/La%20m%C3%A9sange%20du%20Val%20d'Oise/76561198355389674",
+        ),
+        label: None,
+        main: true,
+    };
+    let player = PlayerWithStats::new(&registered_player, parse_player_stat(PAYLOAD).unwrap());
+
+    assert_eq!(
+        player
+            .get_pretty_counters("QuickMatch", catalog().messages(Locale::En))
+            .unwrap(),
+        "122 goals, 58 assists, 26 saves, 14 MVP"
+    );
+    assert_eq!(
+        player
+            .get_pretty_mode_record("Ranked", catalog().messages(Locale::Fr))
+            .unwrap(),
+        "101V 79D"
+    );
+    assert!(
+        player
+            .get_pretty_counters("Tournament", catalog().messages(Locale::En))
+            .is_none()
+    );
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use rebot::{
+    locale::Locale,
+    model::player_stat::{Division, League, RankValue, UggRank},
+};
+use serde_json::json;
+
+use common::catalog;
+
+fn rank(current_league: i32, current_division: i32) -> UggRank {
+    serde_json::from_value(json!({
+        "current_league": current_league,
+        "current_division": current_division,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn ranks_are_ordered() {
+    let mut ranks = vec![
+        RankValue::from(Some(&rank(4, 2))),
+        RankValue::Unranked,
+        RankValue::from(Some(&rank(6, 0))),
+        RankValue::from(Some(&rank(4, 0))),
+        RankValue::from(Some(&rank(0, 1))),
+    ];
+    ranks.sort();
+
+    assert_eq!(
+        ranks,
+        vec![
+            RankValue::Unranked,
+            RankValue::Ranked(League::Bronze, Division::Two),
+            RankValue::Ranked(League::Diamond, Division::Three),
+            RankValue::Ranked(League::Diamond, Division::One),
+            RankValue::Ranked(League::Elite, Division::Three),
+        ]
+    );
+}
+
+#[test]
+fn unknown_values_survive_a_round_trip() {
+    let future = rank(9, 7);
+
+    assert_eq!(future.current_league, League::Unknown(9));
+    assert_eq!(future.current_division, Division::Unknown(7));
+    assert!(future.value() > rank(6, 0).value());
+    assert_eq!(
+        serde_json::to_value(&future).unwrap(),
+        json!({ "current_league": 9, "current_division": 7 })
+    );
+}
+
+#[test]
+fn ranks_are_named_in_every_locale() {
+    let templates = catalog();
+    let (fr, en) = (
+        templates.messages(Locale::Fr),
+        templates.messages(Locale::En),
+    );
+
+    assert_eq!(rank(4, 1).value().name(fr), "diamant 2");
+    assert_eq!(rank(4, 1).value().name(en), "Diamond 2");
+    assert_eq!(rank(6, 0).value().name(fr), "élite");
+    assert_eq!(RankValue::Unranked.name(en), "unranked");
+    assert_eq!(rank(9, 0).value().name(fr), "ligue inconnue 9 3");
+    assert_eq!(rank(2, 5).value().name(en), "Gold unknown division 5");
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use rebot::{
+    locale::Locale,
+    model::player_stat::{Division, League, RankValue},
+    player_store::{PlayerStore, RankChange, RegisteredPlayer},
+    stats_source::PlayerFetch,
+};
+use serde_json::{Value, json};
+
+use common::{LISTING_GUILD_ID, MAIN_GUILD_ID, load_db_v4};
+
+const PAYLOAD: &str = include_str!("fixtures/ugg/steam-76561198355389674.json");
+const ANNOUNCEMENT_CHANNEL_ID: u64 = 111111111111111111;
+
+fn fetch(profile: &RegisteredPlayer, rank: Option<(i32, i32)>) -> PlayerFetch {
+    let mut payload: Value = serde_json::from_str(PAYLOAD).unwrap();
+    payload["rank"] = match rank {
+        Some((current_league, current_division)) => json!({
+            "current_league": current_league,
+            "current_division": current_division,
+        }),
+        None => Value::Null,
+    };
+
+    PlayerFetch::new(
+        profile,
+        Ok(serde_json::from_value(payload).unwrap()),
+        Duration::ZERO,
+    )
+}
+
+/// Refreshes the sample, only the steam account having a previous snapshot, in diamond 2.
+fn refresh_with_rank(store: &mut PlayerStore, rank: Option<(i32, i32)>) -> Vec<RankChange> {
+    let profiles = store.get_unique_profiles();
+    let fetches = profiles.iter().map(|p| fetch(p, rank)).collect();
+
+    store
+        .apply_refresh(&profiles, fetches, Instant::now())
+        .rank_changes
+}
+
+#[test]
+fn promotion_is_detected() {
+    let mut store = load_db_v4();
+    let rank_changes = refresh_with_rank(&mut store, Some((5, 2)));
+
+    assert_eq!(rank_changes.len(), 1);
+    let rank_change = &rank_changes[0];
+    assert_eq!(rank_change.display_name, "La mésange du Val d'Oise");
+    assert_eq!(
+        RankValue::from(rank_change.from.as_ref()),
+        RankValue::Ranked(League::Diamond, Division::Two)
+    );
+    assert_eq!(
+        rank_change.to.value(),
+        RankValue::Ranked(League::Master, Division::One)
+    );
+    assert!(rank_change.is_promotion());
+
+    // Compared against the new snapshot from now on
+    assert!(refresh_with_rank(&mut store, Some((5, 2))).is_empty());
+}
+
+#[test]
+fn demotion_is_detected() {
+    let mut store = load_db_v4();
+    let rank_changes = refresh_with_rank(&mut store, Some((4, 0)));
+
+    assert_eq!(rank_changes.len(), 1);
+    assert!(!rank_changes[0].is_promotion());
+}
+
+#[test]
+fn same_or_lost_rank_is_not_a_change() {
+    let mut store = load_db_v4();
+    assert!(refresh_with_rank(&mut store, Some((4, 1))).is_empty());
+    assert!(refresh_with_rank(&mut store, None).is_empty());
+}
+
+#[test]
+fn announced_in_the_guilds_having_a_channel() {
+    let mut store = load_db_v4();
+    store.set_announcement_channel(LISTING_GUILD_ID, Some(ANNOUNCEMENT_CHANNEL_ID));
+    store.set_guild_locale(LISTING_GUILD_ID, Locale::En);
+    let rank_changes = refresh_with_rank(&mut store, Some((5, 2)));
+
+    let announcements = store.get_rank_change_announcements(&rank_changes);
+    assert_eq!(announcements.len(), 1);
+    assert_eq!(announcements[0].channel_id, ANNOUNCEMENT_CHANNEL_ID);
+    assert_eq!(announcements[0].locale, Locale::En);
+
+    store.set_announcement_channel(MAIN_GUILD_ID, Some(ANNOUNCEMENT_CHANNEL_ID + 1));
+    assert_eq!(store.get_rank_change_announcements(&rank_changes).len(), 2);
+}
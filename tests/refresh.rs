@@ -0,0 +1,90 @@
+mod common;
+
+use std::sync::Arc;
+
+use rebot::{
+    player_store::{PlayerStore, RefreshReport, RegisteredPlayer, SharedPlayerStore},
+    refresh_job::RefreshJobs,
+    scraper::ScrapeError,
+    stats_source::fixture::FixtureSource,
+};
+use tokio::sync::RwLock;
+
+use common::{DISCORD_ID, LISTING_GUILD_ID, load_db_v4};
+
+/// Only has the steam account of the sample, the xbox one is unknown
+const FIXTURE_DIR: &str = "tests/fixtures/ugg";
+const NEWCOMER_URL: &str = "https://u.gg/rematch/profile/psn/Nouvelle%20m%C3%A9sange/9876543210";
+
+async fn refresh(store: PlayerStore) -> (SharedPlayerStore, Arc<RefreshReport>) {
+    let store = Arc::new(RwLock::new(store));
+    let jobs = RefreshJobs::new(store.clone(), Arc::new(FixtureSource::new(FIXTURE_DIR)));
+    let report = jobs.start().await.wait().await.unwrap();
+
+    (store, report)
+}
+
+#[tokio::test]
+async fn fetched_profiles_get_fresh_stats() {
+    let (store, report) = refresh(load_db_v4()).await;
+    assert_eq!(report.success_count(), 1);
+
+    let store = store.read().await;
+    let steam = store
+        .get_account_stat(LISTING_GUILD_ID, DISCORD_ID, "steam")
+        .unwrap();
+    assert!(!steam.stale);
+    assert!(steam.last_refreshed_at.is_some());
+    assert_eq!(steam.level, 43);
+    assert_eq!(steam.get_all_matches(), 260);
+    assert_eq!(steam.get_wins(), 147);
+}
+
+#[tokio::test]
+async fn failures_are_reported_per_profile() {
+    let mut store = load_db_v4();
+    store.registered_players.push(RegisteredPlayer {
+        guild_id: LISTING_GUILD_ID,
+        discord_id: 234567890123456789,
+        rematch_url: String::from(NEWCOMER_URL),
+        label: None,
+        main: true,
+    });
+    let (store, report) = refresh(store).await;
+
+    let failures = report.failures();
+    assert_eq!(failures.len(), 2);
+    assert!(
+        failures
+            .iter()
+            .all(|(_, e)| matches!(e, ScrapeError::ProfileNotFound))
+    );
+
+    // The name of a profile known from an earlier refresh is kept for the summary
+    let (xbox, _) = failures
+        .iter()
+        .find(|(p, _)| p.rematch_url != NEWCOMER_URL)
+        .unwrap();
+    assert_eq!(xbox.display_name.as_deref(), Some("Petite mésange"));
+    let (newcomer, _) = failures
+        .iter()
+        .find(|(p, _)| p.rematch_url == NEWCOMER_URL)
+        .unwrap();
+    assert_eq!(newcomer.display_name, None);
+
+    // Never fetched, nothing to show
+    let store = store.read().await;
+    assert!(!store.players.iter().any(|p| p.rematch_url == NEWCOMER_URL));
+}
+
+#[tokio::test]
+async fn failed_profiles_keep_their_last_stats() {
+    let (store, _) = refresh(load_db_v4()).await;
+
+    let store = store.read().await;
+    let xbox = store.get_player_stat(LISTING_GUILD_ID, DISCORD_ID).unwrap();
+    assert_eq!(xbox.display_name, "Petite mésange");
+    assert!(xbox.stale);
+    assert_eq!(xbox.get_all_matches(), 50);
+    assert_eq!(store.get_all_players_stat(LISTING_GUILD_ID).len(), 2);
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rebot::{
+    model::player_stat::UggPlayerStat,
+    player_store::{RegisteredPlayer, SharedPlayerStore},
+    refresh_job::RefreshJobs,
+    scraper::ScrapeError,
+    stats_source::StatsSource,
+};
+use tokio::sync::{RwLock, Semaphore};
+
+use common::{DISCORD_ID, MAIN_GUILD_ID, load_db_v4};
+
+/// Fails every fetch, once the test lets it go on
+struct GatedSource {
+    gate: Semaphore,
+}
+
+#[async_trait]
+impl StatsSource for GatedSource {
+    async fn get_player_stats(&self, _: &RegisteredPlayer) -> Result<UggPlayerStat, ScrapeError> {
+        let _permit = self.gate.acquire().await.unwrap();
+        Err(ScrapeError::RequestNotFound)
+    }
+}
+
+fn setup() -> (SharedPlayerStore, Arc<GatedSource>, RefreshJobs) {
+    let store = Arc::new(RwLock::new(load_db_v4()));
+    let source = Arc::new(GatedSource {
+        gate: Semaphore::new(0),
+    });
+    let jobs = RefreshJobs::new(store.clone(), source.clone());
+
+    (store, source, jobs)
+}
+
+#[tokio::test]
+async fn store_stays_available_while_scraping() {
+    let (store, source, jobs) = setup();
+
+    let job = jobs.start().await;
+    assert!(job.started);
+    assert!(!jobs.start().await.started);
+    // Let the job reach the gate, scraping without holding the store
+    tokio::task::yield_now().await;
+    assert!(
+        store
+            .try_read()
+            .unwrap()
+            .get_player_stat(MAIN_GUILD_ID, DISCORD_ID)
+            .is_some()
+    );
+
+    source.gate.add_permits(1);
+    let report = job.wait().await.unwrap();
+    assert_eq!(report.failures().len(), 2);
+
+    {
+        let store = store.read().await;
+        let player = store.get_player_stat(MAIN_GUILD_ID, DISCORD_ID).unwrap();
+        assert!(player.stale);
+    }
+    assert!(jobs.start().await.started);
+}
+
+#[tokio::test]
+async fn profiles_forgotten_while_scraping_are_dropped() {
+    let (store, source, jobs) = setup();
+
+    let job = jobs.start().await;
+    assert_eq!(store.write().await.forget_player(DISCORD_ID).count, 4);
+
+    source.gate.add_permits(1);
+    let report = job.wait().await.unwrap();
+    assert!(report.players.is_empty());
+    assert!(
+        store
+            .read()
+            .await
+            .get_all_players_stat(MAIN_GUILD_ID)
+            .is_empty()
+    );
+}
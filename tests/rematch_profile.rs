@@ -0,0 +1,60 @@
+use rebot::model::rematch_profile::{Platform, ProfileError, RematchProfile};
+
+const MESANGE_URL: &str =
+    "https://u.gg/rematch/profile/steam/La%20m%C3%A9sange%20du%20Val%20d'Oise/76561198355389674";
+
+#[test]
+fn parses_a_profile_url() {
+    let profile = RematchProfile::parse(MESANGE_URL).unwrap();
+
+    assert_eq!(profile.platform, Platform::Steam);
+    assert_eq!(profile.display_name, "La mésange du Val d'Oise");
+    assert_eq!(profile.platform_id, "76561198355389674");
+    assert_eq!(profile.to_url(), MESANGE_URL);
+}
+
+#[test]
+fn canonicalizes_other_spellings() {
+    for rematch_url in [
+        "u.gg/rematch/profile/steam/La mésange du Val d'Oise/76561198355389674",
+        "https://www.u.gg/rematch/profile/Steam/La%20m%c3%a9sange%20du%20Val%20d%27Oise/76561198355389674/?tab=overview#top",
+    ] {
+        assert_eq!(
+            RematchProfile::parse(rematch_url).unwrap().to_url(),
+            MESANGE_URL
+        );
+    }
+}
+
+#[test]
+fn rejects_other_urls() {
+    assert_eq!(
+        RematchProfile::parse("https://u.gg/rematch/profile/stadia/Someone/42").unwrap_err(),
+        ProfileError::UnknownPlatform(String::from("stadia"))
+    );
+    assert_eq!(
+        RematchProfile::parse("https://u.gg/rematch/profile/xbox/Someone").unwrap_err(),
+        ProfileError::MissingPlatformId
+    );
+    assert_eq!(
+        RematchProfile::parse("https://example.com/rematch/profile/xbox/Someone/42").unwrap_err(),
+        ProfileError::NotAProfile
+    );
+    assert_eq!(
+        RematchProfile::parse("https://u.gg/rematch/profile/xbox/Someone/42/more").unwrap_err(),
+        ProfileError::NotAProfile
+    );
+}
+
+#[test]
+fn same_profile_whatever_the_display_name() {
+    let renamed =
+        RematchProfile::parse("https://u.gg/rematch/profile/steam/Nouveau%20nom/76561198355389674")
+            .unwrap();
+
+    assert!(
+        RematchProfile::parse(MESANGE_URL)
+            .unwrap()
+            .is_same_profile(&renamed)
+    );
+}
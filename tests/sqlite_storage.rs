@@ -0,0 +1,138 @@
+mod common;
+
+use std::{fs, path::Path};
+
+use chrono::{TimeDelta, Utc};
+use rebot::{
+    history::StatsSnapshot,
+    locale::Locale,
+    player_store::AccountsDisplay,
+    storage::{Storage, migration::MigrationContext, sqlite::SqliteStorage},
+};
+
+use common::{DB_V4, DISCORD_ID, LISTING_GUILD_ID, MAIN_GUILD_ID, load_db_v4, temp_dir};
+
+const MESANGE_URL: &str =
+    "https://u.gg/rematch/profile/steam/La%20m%C3%A9sange%20du%20Val%20d'Oise/76561198355389674";
+
+fn open(dir: &Path, import_path: Option<&Path>) -> SqliteStorage {
+    SqliteStorage::open(
+        dir.join("db.sqlite").to_str().unwrap(),
+        import_path.map(|path| path.to_str().unwrap()),
+        MigrationContext::default(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn store_survives_a_round_trip() {
+    let dir = temp_dir("sqlite-round-trip");
+    let storage = open(&dir, None);
+    assert!(storage.load().unwrap().is_none());
+
+    let mut store = load_db_v4();
+    store.set_accounts_display(MAIN_GUILD_ID, AccountsDisplay::List);
+    store.set_user_locale(DISCORD_ID, Some(Locale::En));
+    let latest = store.history.get_latest(MESANGE_URL).unwrap().clone();
+    store.history.record(
+        MESANGE_URL,
+        StatsSnapshot {
+            taken_at: latest.taken_at + TimeDelta::days(1),
+            matches_played: latest.matches_played + 5,
+            ..latest
+        },
+    );
+    storage.save(&store).unwrap();
+
+    let loaded = open(&dir, None).load().unwrap().unwrap();
+    assert_eq!(loaded.registered_players.len(), 4);
+    assert_eq!(loaded.players.len(), 2);
+    assert_eq!(
+        loaded
+            .get_player_stat(LISTING_GUILD_ID, DISCORD_ID)
+            .unwrap()
+            .display_name,
+        "Petite mésange"
+    );
+    assert_eq!(loaded.get_all_players_stat(MAIN_GUILD_ID).len(), 2);
+    assert_eq!(
+        loaded.get_locale(Some(LISTING_GUILD_ID), DISCORD_ID),
+        Locale::En
+    );
+    assert_eq!(loaded.history.get_snapshots(MESANGE_URL).len(), 2);
+    assert_eq!(
+        loaded
+            .history
+            .get_latest(MESANGE_URL)
+            .unwrap()
+            .matches_played,
+        255
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn pruned_and_forgotten_snapshots_are_deleted() {
+    let dir = temp_dir("sqlite-prune");
+    let storage = open(&dir, None);
+    assert!(storage.load().unwrap().is_none());
+
+    let mut store = load_db_v4();
+    storage.save(&store).unwrap();
+    store.forget_player(DISCORD_ID);
+    store.history.prune(Some(TimeDelta::days(1)), Utc::now());
+    storage.save(&store).unwrap();
+
+    let loaded = open(&dir, None).load().unwrap().unwrap();
+    assert!(loaded.registered_players.is_empty());
+    assert!(loaded.history.get_snapshots(MESANGE_URL).is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn json_database_is_imported_once() {
+    let dir = temp_dir("sqlite-import");
+    let json_path = dir.join("db.json");
+    fs::write(&json_path, DB_V4).unwrap();
+
+    let imported = open(&dir, Some(&json_path)).load().unwrap().unwrap();
+    assert_eq!(imported.registered_players.len(), 4);
+    assert_eq!(imported.history.get_snapshots(MESANGE_URL).len(), 1);
+
+    // Later starts read SQLite only, whatever happens to the JSON file
+    fs::write(&json_path, "{}").unwrap();
+    let loaded = open(&dir, Some(&json_path)).load().unwrap().unwrap();
+    assert_eq!(loaded.registered_players.len(), 4);
+    assert_eq!(fs::read_to_string(&json_path).unwrap(), "{}");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn corrupt_json_database_is_left_untouched() {
+    let dir = temp_dir("sqlite-import-corrupt");
+    let json_path = dir.join("db.json");
+    fs::write(&json_path, "{\"registered_players\": [").unwrap();
+
+    assert!(open(&dir, Some(&json_path)).load().is_err());
+    assert_eq!(
+        fs::read_to_string(&json_path).unwrap(),
+        "{\"registered_players\": ["
+    );
+    assert!(fs::read_dir(&dir).unwrap().all(|entry| {
+        !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .contains("corrupt")
+    }));
+
+    // Not marked as imported, fixing the file and restarting imports it
+    fs::write(&json_path, DB_V4).unwrap();
+    let imported = open(&dir, Some(&json_path)).load().unwrap().unwrap();
+    assert_eq!(imported.registered_players.len(), 4);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,105 @@
+use std::{
+    env, fs,
+    fs::File,
+    time::{Duration, SystemTime},
+};
+
+use rebot::{
+    locale::Locale,
+    model::player_stat::{Division, League, RankValue},
+    templates::Templates,
+};
+
+fn write_templates(name: &str, json: &str, modified: SystemTime) -> String {
+    let path = env::temp_dir().join(format!(
+        "rebot-templates-{}-{}.json",
+        name,
+        std::process::id()
+    ));
+    fs::write(&path, json).unwrap();
+    File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn variants_override_the_catalog() {
+    let path = write_templates(
+        "override",
+        r#"{"fr": {"stats.try_harder": ["{player} : {hours}h", "{hours}h pour {player}"]}}"#,
+        SystemTime::now(),
+    );
+    let templates = Templates::new(&path);
+
+    let message = templates
+        .messages(Locale::Fr)
+        .get("stats.try_harder", &[("player", &"Zizou"), ("hours", &42)]);
+    assert!(
+        message == "Zizou : 42h" || message == "42h pour Zizou",
+        "{}",
+        message
+    );
+
+    assert_eq!(
+        templates
+            .messages(Locale::En)
+            .get("refresh.progress", &[("done", &7), ("total", &15)]),
+        Locale::En.message("refresh.progress", &[("done", &7), ("total", &15)])
+    );
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn edited_file_is_reloaded() {
+    let start = SystemTime::now() - Duration::from_secs(60);
+    let path = write_templates("reload", r#"{"en": {"language.user": ["Old"]}}"#, start);
+    let templates = Templates::new(&path);
+    assert_eq!(
+        templates.messages(Locale::En).get("language.user", &[]),
+        "Old"
+    );
+
+    write_templates("reload", "{not json", start + Duration::from_secs(10));
+    assert_eq!(
+        templates.messages(Locale::En).get("language.user", &[]),
+        "Old"
+    );
+
+    write_templates(
+        "reload",
+        r#"{"en": {"language.user": ["New"]}}"#,
+        start + Duration::from_secs(20),
+    );
+    assert_eq!(
+        templates.messages(Locale::En).get("language.user", &[]),
+        "New"
+    );
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        templates.messages(Locale::En).get("language.user", &[]),
+        Locale::En.message("language.user", &[])
+    );
+}
+
+#[test]
+fn rank_names_can_be_overridden() {
+    let path = write_templates(
+        "rank",
+        r#"{"en": {"rank.league.diamond": ["Diamant"], "rank.ranked": ["{league} {division} ♦"]}}"#,
+        SystemTime::now(),
+    );
+    let templates = Templates::new(&path);
+    let rank = RankValue::Ranked(League::Diamond, Division::One);
+
+    assert_eq!(rank.name(templates.messages(Locale::En)), "Diamant 1 ♦");
+    assert_eq!(rank.name(templates.messages(Locale::Fr)), "diamant 1");
+
+    fs::remove_file(path).unwrap();
+}